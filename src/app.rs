@@ -0,0 +1,1342 @@
+// The launcher's state and how events change it.
+//
+// `update` is the only place the state changes. It takes an event (a key, a
+// click, a tick of the clock, or the outcome of an earlier effect) and returns
+// the effects to carry out. Launching, saving and reading the config file are
+// `Effect`s performed by `run_app`, which feeds their results back in as
+// events, so a key sequence can be replayed without a terminal or a disk.
+
+use crate::config::{self, App, Config, FileStamp, LaunchMode};
+use crate::history::History;
+use crate::keys::{self, Action, Keymap, Motion, VimStep};
+use crate::view::{Layout, View};
+use crate::{argv, launcher, query};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+// How long a partly typed hotkey sequence waits for its next key
+const CHORD_TIMEOUT: Duration = Duration::from_millis(1500);
+// Second click on the same row within this counts as a double-click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+// Rows scrolled per wheel notch
+const WHEEL_STEP: usize = 3;
+// Characters visible in a form input box
+pub const FORM_INPUT_WIDTH: usize = 39;
+
+#[derive(Clone, Debug)]
+pub struct FormField {
+    pub label: &'static str,
+    pub value: String,
+    // In characters, not bytes
+    pub cursor_pos: usize,
+}
+
+impl FormField {
+    // First character shown in the input box; longer values scroll to keep the cursor visible
+    pub fn scroll(&self) -> usize {
+        self.cursor_pos.saturating_sub(FORM_INPUT_WIDTH - 1)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FormState {
+    pub title: &'static str,
+    pub fields: Vec<FormField>,
+    pub active_field: usize,
+    pub error_message: Option<String>,
+    pub is_edit: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ModalState {
+    None,
+    Form,
+    DeleteConfirm,
+    ThemeSelect,
+    SaveConflict,
+}
+
+// One line of the list pane; apps refer to their index in the ranked matches
+pub enum ListRow<'a> {
+    Header { group: &'a str, count: usize },
+    App(usize),
+}
+
+// Identifies a row across rebuilds of the list
+enum RowId {
+    App(String),
+    Group(String),
+}
+
+// Brief message shown under the launcher box
+pub struct Toast {
+    pub message: String,
+    pub is_error: bool,
+    shown_at: Instant,
+}
+
+impl Toast {
+    fn new(message: impl Into<String>, is_error: bool, now: Instant) -> Self {
+        Self { message: message.into(), is_error, shown_at: now }
+    }
+
+    // Warning for a `[keys]` table that could not be applied
+    fn for_keymap(config: &Config, now: Instant) -> Option<Self> {
+        let errors = keys::Keymap::from_config(config).err()?;
+        let more = if errors.len() > 1 { format!(" (+{} more)", errors.len() - 1) } else { String::new() };
+        Some(Self::new(format!("Using default keys: {}{}", errors[0], more), true, now))
+    }
+}
+
+pub struct PredefinedTheme {
+    pub name: &'static str,
+    border_color: &'static str,
+    accent_color: &'static str,
+    text_color: &'static str,
+    dim_color: &'static str,
+}
+
+pub const PREDEFINED_THEMES: &[PredefinedTheme] = &[
+    PredefinedTheme { name: "Default Blue", border_color: "blue", accent_color: "cyan", text_color: "white", dim_color: "dark_grey" },
+    PredefinedTheme { name: "Cyberpunk Neon", border_color: "cyan", accent_color: "magenta", text_color: "white", dim_color: "grey" },
+    PredefinedTheme { name: "Nordic Frost", border_color: "dark_cyan", accent_color: "cyan", text_color: "grey", dim_color: "dark_grey" },
+    PredefinedTheme { name: "Gruvbox Autumn", border_color: "dark_yellow", accent_color: "yellow", text_color: "white", dim_color: "grey" },
+    PredefinedTheme { name: "Dracula Night", border_color: "dark_magenta", accent_color: "magenta", text_color: "white", dim_color: "dark_grey" },
+    PredefinedTheme { name: "Matrix Terminal", border_color: "dark_green", accent_color: "green", text_color: "green", dim_color: "dark_grey" },
+    PredefinedTheme { name: "Sunset Crimson", border_color: "dark_red", accent_color: "red", text_color: "white", dim_color: "dark_grey" },
+];
+
+// Parses "KEY=VALUE KEY2='a value'" from the form's Env field
+fn parse_env_pairs(input: &str) -> Result<Option<BTreeMap<String, String>>, String> {
+    let mut vars = BTreeMap::new();
+    for pair in argv::split(input).map_err(|e| format!("Env: {}", e))? {
+        match pair.split_once('=') {
+            Some((k, v)) if !k.is_empty() => {
+                vars.insert(k.to_string(), v.to_string());
+            }
+            _ => return Err(format!("Env entry '{}' must be KEY=VALUE.", pair)),
+        }
+    }
+    Ok(if vars.is_empty() { None } else { Some(vars) })
+}
+
+// Tags separated by spaces or commas; a leading `#` is dropped and duplicates removed
+fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input.split(|c: char| c == ',' || c.is_whitespace()).map(|t| t.trim_start_matches('#')) {
+        if !tag.is_empty() && !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+// Empty means the default, foreground
+fn parse_mode(input: &str) -> Result<Option<LaunchMode>, String> {
+    if input.is_empty() {
+        return Ok(None);
+    }
+    LaunchMode::parse(&input.to_lowercase()).map(Some).ok_or_else(|| {
+        let names: Vec<&str> = LaunchMode::ALL.iter().map(|m| m.name()).collect();
+        format!("Mode '{}' is not one of {}.", input, names.join(", "))
+    })
+}
+
+fn parse_yes_no(input: &str) -> Result<Option<bool>, String> {
+    match input.to_lowercase().as_str() {
+        "" | "n" | "no" | "false" => Ok(None),
+        "y" | "yes" | "true" => Ok(Some(true)),
+        _ => Err(format!("'{}' is not yes or no.", input)),
+    }
+}
+
+// What a typed hotkey sequence leads to
+enum ChordStep<'a> {
+    Launch(&'a App),
+    // Longer keys still start with it; wait for the next key
+    Pending,
+    NoMatch,
+}
+
+fn chord_step<'a>(apps: &'a [App], typed: &str) -> ChordStep<'a> {
+    if apps.iter().any(|a| a.key.len() > typed.len() && a.key.starts_with(typed)) {
+        ChordStep::Pending
+    } else if let Some(app) = apps.iter().find(|a| a.key == typed) {
+        ChordStep::Launch(app)
+    } else {
+        ChordStep::NoMatch
+    }
+}
+
+// Byte offset of the `pos`th character, or the end; text cursors count characters
+fn byte_offset(text: &str, pos: usize) -> usize {
+    text.char_indices().nth(pos).map_or(text.len(), |(idx, _)| idx)
+}
+
+// Filter apps dynamically: tag filters first, then best fuzzy matches, frecency breaks ties.
+// Returns the matches and the list rows showing them; `now` is the unix time frecency is taken at.
+fn list_rows<'a>(config: &'a Config, history: &History, query: &query::Query, collapsed: &BTreeSet<String>, now: u64) -> (Vec<(&'a App, query::AppMatch, u64)>, Vec<ListRow<'a>>) {
+    let mut ranked: Vec<(&App, query::AppMatch, u64)> = config.apps.iter()
+        .filter(|app| query.matches_tags(app))
+        .filter_map(|app| {
+            query.match_app(app)
+                .map(|m| (app, m, history.frecency(app, now)))
+        })
+        .collect();
+    ranked.sort_by_key(|(_, m, frecency)| std::cmp::Reverse((m.score, *frecency)));
+
+    // Ungrouped apps first, then each group (in config order) under a header;
+    // while searching the matches are listed flat in rank order
+    let mut rows: Vec<ListRow> = Vec::new();
+    let mut groups: Vec<&str> = Vec::new();
+    for group in config.apps.iter().filter_map(App::group) {
+        if !groups.contains(&group) {
+            groups.push(group);
+        }
+    }
+    if groups.is_empty() || !query.is_empty() {
+        rows.extend((0..ranked.len()).map(ListRow::App));
+    } else {
+        rows.extend(ranked.iter().enumerate().filter(|(_, (app, _, _))| app.group().is_none()).map(|(i, _)| ListRow::App(i)));
+        for group in groups {
+            let members: Vec<usize> = ranked.iter().enumerate().filter(|(_, (app, _, _))| app.group() == Some(group)).map(|(i, _)| i).collect();
+            rows.push(ListRow::Header { group, count: members.len() });
+            if !collapsed.contains(group) {
+                rows.extend(members.into_iter().map(ListRow::App));
+            }
+        }
+    }
+    (ranked, rows)
+}
+
+pub enum Event {
+    Key(KeyEvent),
+    Mouse(MouseEvent),
+    Resize(u16, u16),
+    // No input for a while: expires the toast and a pending hotkey sequence
+    Tick,
+    // The config file was touched by something
+    ConfigChanged,
+    // A launched app exited, or was started without waiting for it (the code is
+    // None then, and when it was killed by a signal)
+    Launched { app: App, code: Option<i32> },
+    // An app in the background could not be started
+    LaunchFailed(String),
+    // Outcome of `Effect::Save` or `Effect::Overwrite`: the config as written
+    Saved(io::Result<Config>),
+    // Outcome of `Effect::Load` or `Effect::ReloadIfChanged`
+    Loaded(io::Result<Config>),
+}
+
+#[derive(Debug)]
+pub enum Effect {
+    Quit,
+    Launch(App),
+    // Write the config unless the file changed since `stamp`
+    Save(Config),
+    // Write the config over whatever the file holds now
+    Overwrite(Config),
+    // Read the config file
+    Load,
+    // Read the config file if it no longer matches this stamp
+    ReloadIfChanged(Option<FileStamp>),
+    SaveHistory,
+}
+
+// Which answer to the save conflict dialog is waiting for the file to be read
+enum Resolve {
+    Reload,
+    Merge,
+}
+
+pub struct State {
+    pub config: Config,
+    // An instant and the unix time at it, to tell the wall-clock time of later events
+    started: (Instant, u64),
+    // Unix time of the latest event; ranking and history are as of then
+    unix_time: u64,
+    // Advanced by every new app id
+    id_seed: u64,
+    // What was last read from or written to the file, to spot unsaved changes
+    saved: Config,
+    // None for an in-memory config that is never saved
+    pub config_path: Option<PathBuf>,
+    pub history: History,
+    // Terminal size the layout is computed for
+    pub size: (u16, u16),
+    selected: usize,
+    // First list row shown; kept between frames so the view does not jump
+    scroll_offset: usize,
+    // When and on which screen row the last click landed, to spot double-clicks
+    last_click: Option<(Instant, u16)>,
+    modal_state: ModalState,
+    active_form: Option<FormState>,
+    conflict_note: Option<String>,
+    resolving: Option<Resolve>,
+    reload_pending: bool,
+    reselect: Option<RowId>,
+    collapsed: BTreeSet<String>,
+    toast: Option<Toast>,
+    selected_theme_idx: usize,
+    search_query: String,
+    search_active: bool,
+    search_cursor_pos: usize,
+    // Hotkey sequence typed so far, e.g. "g" while waiting for "gl"
+    chord: String,
+    chord_started: Instant,
+    // Vim mode: the leader was pressed, the first half of `gg`/`dd`, and the `:` command line
+    leader_pending: bool,
+    vim_pending: Option<char>,
+    command_active: bool,
+    command_line: String,
+}
+
+// Applies one event; the effects are for the caller to carry out, in order
+pub fn update(mut state: State, event: Event, now: Instant) -> (State, Vec<Effect>) {
+    let mut effects = Vec::new();
+    state.unix_time = state.started.1 + now.saturating_duration_since(state.started.0).as_secs();
+    match event {
+        Event::Key(key_event) => state.on_key(key_event, now, &mut effects),
+        Event::Mouse(mouse) => {
+            if let Some(code) = state.on_mouse(mouse, now, &mut effects) {
+                state.on_key(KeyEvent::new(code, KeyModifiers::NONE), now, &mut effects);
+            }
+        }
+        Event::Resize(cols, rows) => state.size = (cols, rows),
+        Event::Tick => state.on_tick(now, &mut effects),
+        Event::ConfigChanged => state.reload_pending = true,
+        Event::Launched { app, code } => {
+            if matches!(app.mode, Some(LaunchMode::Detach | LaunchMode::Terminal)) {
+                state.toast = Some(Toast::new(format!("Started {}", app.name), false, now));
+            }
+            state.history.record(&app, code, state.unix_time);
+            effects.push(Effect::SaveHistory);
+        }
+        Event::LaunchFailed(message) => state.toast = Some(Toast::new(message, true, now)),
+        Event::Saved(result) => state.on_saved(result),
+        Event::Loaded(result) => state.on_loaded(result, now, &mut effects),
+    }
+    state.settle();
+    (state, effects)
+}
+
+impl State {
+    // `unix_time` is the wall-clock time at `now`; later times are told from the instants
+    // events arrive at. New app ids follow from `id_seed`.
+    pub fn new(config: Config, config_path: Option<PathBuf>, history: History, size: (u16, u16), now: Instant, unix_time: u64, id_seed: u64) -> Self {
+        Self {
+            started: (now, unix_time),
+            unix_time,
+            id_seed,
+            toast: Toast::for_keymap(&config, now),
+            saved: config.clone(),
+            config,
+            config_path,
+            history,
+            size,
+            selected: 0,
+            scroll_offset: 0,
+            last_click: None,
+            modal_state: ModalState::None,
+            active_form: None,
+            conflict_note: None,
+            resolving: None,
+            reload_pending: false,
+            reselect: None,
+            collapsed: BTreeSet::new(),
+            selected_theme_idx: 0,
+            search_query: String::new(),
+            search_active: false,
+            search_cursor_pos: 0,
+            chord: String::new(),
+            chord_started: now,
+            leader_pending: false,
+            vim_pending: None,
+            command_active: false,
+            command_line: String::new(),
+        }
+    }
+
+    pub fn layout(&self) -> Layout {
+        Layout::new(self.size.0, self.size.1)
+    }
+
+    // The ranked matches for the current search and the list rows showing them
+    pub fn list(&self) -> (Vec<(&App, query::AppMatch, u64)>, Vec<ListRow<'_>>) {
+        list_rows(&self.config, &self.history, &query::parse(&self.search_query), &self.collapsed, self.unix_time)
+    }
+
+    // What the screen shows; `ranked` and `rows` come from `list`
+    pub fn view<'a>(&'a self, keymap: &'a Keymap, ranked: &'a [(&'a App, query::AppMatch, u64)], rows: &'a [ListRow<'a>]) -> View<'a> {
+        View {
+            config: &self.config,
+            keymap,
+            unsaved: self.config_path.is_none() || self.config != self.saved,
+            ranked,
+            rows,
+            collapsed: &self.collapsed,
+            selected: self.selected,
+            scroll_offset: self.scroll_offset,
+            search_query: &self.search_query,
+            search_active: self.search_active,
+            search_cursor_pos: self.search_cursor_pos,
+            command_line: self.command_active.then_some(self.command_line.as_str()),
+            modal_state: &self.modal_state,
+            form: self.active_form.as_ref(),
+            selected_theme_idx: self.selected_theme_idx,
+            conflict_note: self.conflict_note.as_deref(),
+            toast: self.toast.as_ref(),
+            chord: &self.chord,
+            leader_pending: self.leader_pending,
+        }
+    }
+
+    // Index in `config.apps` of the app on the selected row
+    fn selected_app(&self) -> Option<usize> {
+        let (ranked, rows) = self.list();
+        let Some(ListRow::App(i)) = rows.get(self.selected) else { return None };
+        let app = ranked[*i].0;
+        self.config.apps.iter().position(|a| a.id == app.id)
+    }
+
+    // Keeps the selection on a row and in view after the list changed
+    fn settle(&mut self) {
+        let (ranked, rows) = list_rows(&self.config, &self.history, &query::parse(&self.search_query), &self.collapsed, self.unix_time);
+
+        // Keep the same row selected across reloads and collapsing
+        if let Some(target) = self.reselect.take()
+            && let Some(pos) = rows.iter().position(|row| match (row, &target) {
+                (ListRow::App(i), RowId::App(id)) => ranked[*i].0.id == *id,
+                (ListRow::Header { group, .. }, RowId::Group(target)) => group == target,
+                _ => false,
+            })
+        {
+            self.selected = pos;
+        }
+
+        // Clamp selected
+        if !rows.is_empty() && self.selected >= rows.len() {
+            self.selected = rows.len() - 1;
+        }
+        self.scroll_offset = Layout::new(self.size.0, self.size.1).scroll(self.selected, self.scroll_offset, rows.len());
+    }
+
+    fn on_tick(&mut self, now: Instant, effects: &mut Vec<Effect>) {
+        if self.toast.as_ref().is_some_and(|t| now.duration_since(t.shown_at) >= TOAST_DURATION) {
+            self.toast = None;
+        }
+        // A key that also starts longer ones fires once the wait is over
+        if (!self.chord.is_empty() || self.leader_pending) && now.duration_since(self.chord_started) >= CHORD_TIMEOUT {
+            self.leader_pending = false;
+            let typed = std::mem::take(&mut self.chord);
+            if let Some(app) = self.config.apps.iter().find(|a| a.key == typed) {
+                effects.push(Effect::Launch(app.clone()));
+            }
+        }
+        // Reloading under an open dialog would swap the app out from under it
+        if self.reload_pending && self.modal_state == ModalState::None && self.config_path.is_some() {
+            self.reload_pending = false;
+            effects.push(Effect::ReloadIfChanged(self.config.stamp));
+        }
+    }
+
+    fn on_saved(&mut self, result: io::Result<Config>) {
+        match result {
+            Ok(written) => {
+                self.config = written.clone();
+                self.saved = written;
+                if matches!(self.modal_state, ModalState::Form | ModalState::SaveConflict) {
+                    self.modal_state = ModalState::None;
+                    self.active_form = None;
+                    self.conflict_note = None;
+                }
+            }
+            Err(e) if self.modal_state == ModalState::SaveConflict => {
+                self.conflict_note = Some(if config::conflict(&e).is_some() { "Changed again on disk, pick again".to_string() } else { e.to_string() });
+            }
+            Err(e) if config::conflict(&e).is_some() => {
+                self.modal_state = ModalState::SaveConflict;
+                self.active_form = None;
+            }
+            Err(e) => {
+                if let Some(form) = self.active_form.as_mut() {
+                    form.error_message = Some(format!("Failed to save: {}", e));
+                }
+            }
+        }
+    }
+
+    fn on_loaded(&mut self, result: io::Result<Config>, now: Instant, effects: &mut Vec<Effect>) {
+        match (self.resolving.take(), result) {
+            // Drop the unsaved change and take what is on disk
+            (Some(Resolve::Reload), Ok(disk)) => {
+                self.config = disk.clone();
+                self.saved = disk;
+                self.modal_state = ModalState::None;
+                self.conflict_note = None;
+            }
+            // Replay the change on top of the other program's version
+            (Some(Resolve::Merge), Ok(disk)) => effects.push(Effect::Save(config::merge(&self.saved, &self.config, disk, &mut self.id_seed))),
+            (Some(_), Err(e)) => self.conflict_note = Some(e.to_string()),
+            (None, _) if self.config != self.saved => {
+                self.toast = Some(Toast::new("Config changed on disk; saving will ask how to resolve it", true, now));
+            }
+            (None, Ok(disk)) => {
+                self.reselect = self.selected_app().map(|i| RowId::App(self.config.apps[i].id.clone()));
+                self.config = disk.clone();
+                self.saved = disk;
+                self.toast = Some(Toast::for_keymap(&self.config, now).unwrap_or_else(|| Toast::new("Config reloaded", false, now)));
+            }
+            (None, Err(e)) => self.toast = Some(Toast::new(format!("Config reload failed: {}", e), true, now)),
+        }
+    }
+
+    // Clicks and the wheel act on the list and dialogs directly; closing or
+    // confirming a dialog is returned as the Esc or Enter key to replay
+    fn on_mouse(&mut self, mouse: MouseEvent, now: Instant, effects: &mut Vec<Effect>) -> Option<KeyCode> {
+        let layout = self.layout();
+        let Layout { start_x, divider_x, search_y, content_start_y, max_items, .. } = layout;
+        let (ranked, rows) = list_rows(&self.config, &self.history, &query::parse(&self.search_query), &self.collapsed, self.unix_time);
+        let (x, y) = (mouse.column, mouse.row);
+        let clicked = mouse.kind == MouseEventKind::Down(MouseButton::Left);
+        let double = clicked && self.last_click.is_some_and(|(at, row)| row == y && now.duration_since(at) <= DOUBLE_CLICK);
+        if clicked {
+            self.last_click = if double { None } else { Some((now, y)) };
+        }
+        let scroll_down = mouse.kind == MouseEventKind::ScrollDown;
+        let scrolled = scroll_down || mouse.kind == MouseEventKind::ScrollUp;
+        let rect = layout.modal_rect(&self.modal_state, self.active_form.as_ref().map_or(0, |f| f.fields.len()));
+        let mut replay = None;
+        match rect {
+            Some((mx, my, mw, mh)) if clicked && (x < mx || x >= mx + mw || y < my || y >= my + mh) => {
+                replay = Some(KeyCode::Esc);
+            }
+            Some((_, my, _, _)) if self.modal_state == ModalState::ThemeSelect => {
+                if scrolled {
+                    replay = Some(if scroll_down { KeyCode::Down } else { KeyCode::Up });
+                } else if clicked
+                    && let Some(idx) = y.checked_sub(my + 2).map(usize::from).filter(|&i| i < PREDEFINED_THEMES.len())
+                {
+                    self.selected_theme_idx = idx;
+                    if double {
+                        replay = Some(KeyCode::Enter);
+                    }
+                }
+            }
+            Some((mx, my, _, _)) if clicked && self.modal_state == ModalState::Form => {
+                if let Some(form) = self.active_form.as_mut()
+                    && let Some(idx) = y.checked_sub(my + 2).map(usize::from).filter(|&i| i < form.fields.len())
+                {
+                    form.active_field = idx;
+                    let field = &mut form.fields[idx];
+                    field.cursor_pos = (field.scroll() + usize::from(x.saturating_sub(mx + 16))).min(field.value.chars().count());
+                }
+            }
+            Some(_) => {}
+            None if scrolled => {
+                let margin = layout.margin();
+                let max_offset = rows.len().saturating_sub(max_items);
+                self.scroll_offset = if scroll_down { (self.scroll_offset + WHEEL_STEP).min(max_offset) } else { self.scroll_offset.saturating_sub(WHEEL_STEP) };
+                // Keep the selection in view so the next frame does not scroll back to it
+                let top = if self.scroll_offset == 0 { 0 } else { self.scroll_offset + margin };
+                let bottom = if self.scroll_offset >= max_offset { rows.len().saturating_sub(1) } else { (self.scroll_offset + max_items).saturating_sub(margin + 1) };
+                self.selected = self.selected.clamp(top.min(bottom), bottom);
+            }
+            None if clicked => {
+                let in_list_pane = x > start_x && x < divider_x;
+                let start_index = self.scroll_offset;
+                let end_index = std::cmp::min(rows.len(), start_index + max_items);
+                self.chord.clear();
+                self.leader_pending = false;
+                if in_list_pane && y == search_y {
+                    self.search_active = true;
+                    self.search_cursor_pos = self.search_query.chars().count();
+                } else if in_list_pane
+                    && y >= content_start_y
+                    && let row = start_index + usize::from(y - content_start_y)
+                    && row < end_index
+                {
+                    self.search_active = false;
+                    self.selected = row;
+                    // Double-clicking launches an app or folds a group
+                    match rows.get(row) {
+                        Some(ListRow::App(idx)) if double => effects.push(Effect::Launch(ranked[*idx].0.clone())),
+                        Some(ListRow::Header { group, .. }) if double => {
+                            if !self.collapsed.remove(*group) {
+                                self.collapsed.insert(group.to_string());
+                            }
+                            self.reselect = Some(RowId::Group(group.to_string()));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            None => {}
+        }
+        replay
+    }
+
+    fn on_key(&mut self, key_event: KeyEvent, now: Instant, effects: &mut Vec<Effect>) {
+        match self.modal_state {
+            ModalState::DeleteConfirm => {
+                match key_event.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') => {
+                        if let Some(idx) = self.selected_app() {
+                            self.config.apps.remove(idx);
+                            effects.push(Effect::Save(self.config.clone()));
+                        }
+                        self.modal_state = ModalState::None;
+                    }
+                    KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                        self.modal_state = ModalState::None;
+                    }
+                    _ => {}
+                }
+            }
+            ModalState::ThemeSelect => {
+                match key_event.code {
+                    KeyCode::Esc => {
+                        self.modal_state = ModalState::None;
+                    }
+                    KeyCode::Up => {
+                        if self.selected_theme_idx > 0 {
+                            self.selected_theme_idx -= 1;
+                        } else {
+                            self.selected_theme_idx = PREDEFINED_THEMES.len() - 1;
+                        }
+                    }
+                    KeyCode::Down => {
+                        if self.selected_theme_idx + 1 < PREDEFINED_THEMES.len() {
+                            self.selected_theme_idx += 1;
+                        } else {
+                            self.selected_theme_idx = 0;
+                        }
+                    }
+                    KeyCode::Enter => {
+                        let selected_theme = &PREDEFINED_THEMES[self.selected_theme_idx];
+                        self.config.theme = Some(config::Theme {
+                            accent_color: Some(selected_theme.accent_color.to_string()),
+                            border_color: Some(selected_theme.border_color.to_string()),
+                            text_color: Some(selected_theme.text_color.to_string()),
+                            dim_color: Some(selected_theme.dim_color.to_string()),
+                        });
+                        self.modal_state = ModalState::None;
+                        effects.push(Effect::Save(self.config.clone()));
+                    }
+                    _ => {}
+                }
+            }
+            ModalState::SaveConflict => {
+                if self.config_path.is_none() {
+                    self.modal_state = ModalState::None;
+                    return;
+                }
+                match key_event.code {
+                    KeyCode::Char('r') => {
+                        self.resolving = Some(Resolve::Reload);
+                        effects.push(Effect::Load);
+                    }
+                    KeyCode::Char('o') => effects.push(Effect::Overwrite(self.config.clone())),
+                    KeyCode::Char('m') => {
+                        self.resolving = Some(Resolve::Merge);
+                        effects.push(Effect::Load);
+                    }
+                    KeyCode::Esc => {
+                        self.modal_state = ModalState::None;
+                        self.conflict_note = None;
+                    }
+                    _ => {}
+                }
+            }
+            ModalState::Form => self.on_form_key(key_event, effects),
+            ModalState::None if self.search_active => {
+                match key_event.code {
+                    KeyCode::Esc => {
+                        self.search_active = false;
+                        self.search_query.clear();
+                        self.search_cursor_pos = 0;
+                    }
+                    KeyCode::Enter | KeyCode::Down | KeyCode::Tab => {
+                        self.search_active = false;
+                    }
+                    KeyCode::Left => {
+                        self.search_cursor_pos = self.search_cursor_pos.saturating_sub(1);
+                    }
+                    KeyCode::Right if self.search_cursor_pos < self.search_query.chars().count() => {
+                        self.search_cursor_pos += 1;
+                    }
+                    KeyCode::Backspace if self.search_cursor_pos > 0 => {
+                        self.search_query.remove(byte_offset(&self.search_query, self.search_cursor_pos - 1));
+                        self.search_cursor_pos -= 1;
+                    }
+                    KeyCode::Delete if self.search_cursor_pos < self.search_query.chars().count() => {
+                        self.search_query.remove(byte_offset(&self.search_query, self.search_cursor_pos));
+                    }
+                    KeyCode::Char(c) => {
+                        self.search_query.insert(byte_offset(&self.search_query, self.search_cursor_pos), c);
+                        self.search_cursor_pos += 1;
+                    }
+                    _ => {}
+                }
+            }
+            ModalState::None => self.on_list_key(key_event, now, effects),
+        }
+    }
+
+    fn on_form_key(&mut self, key_event: KeyEvent, effects: &mut Vec<Effect>) {
+        // An invalid [keys] table falls back to the defaults (reported in a toast)
+        let keymap = Keymap::from_config(&self.config).unwrap_or_default();
+        let current = self.selected_app();
+        let Some(form) = self.active_form.as_mut() else { return };
+        match key_event.code {
+            KeyCode::Esc => {
+                self.modal_state = ModalState::None;
+                self.active_form = None;
+            }
+            KeyCode::Tab | KeyCode::Down => {
+                form.active_field = (form.active_field + 1) % form.fields.len();
+            }
+            KeyCode::BackTab | KeyCode::Up => {
+                form.active_field = (form.active_field + form.fields.len() - 1) % form.fields.len();
+            }
+            KeyCode::Left => {
+                let field = &mut form.fields[form.active_field];
+                if field.cursor_pos > 0 {
+                    field.cursor_pos -= 1;
+                }
+            }
+            KeyCode::Right => {
+                let field = &mut form.fields[form.active_field];
+                if field.cursor_pos < field.value.chars().count() {
+                    field.cursor_pos += 1;
+                }
+            }
+            KeyCode::Backspace => {
+                let field = &mut form.fields[form.active_field];
+                if field.cursor_pos > 0 {
+                    field.value.remove(byte_offset(&field.value, field.cursor_pos - 1));
+                    field.cursor_pos -= 1;
+                }
+            }
+            KeyCode::Delete => {
+                let field = &mut form.fields[form.active_field];
+                if field.cursor_pos < field.value.chars().count() {
+                    field.value.remove(byte_offset(&field.value, field.cursor_pos));
+                }
+            }
+            KeyCode::Char(c) => {
+                let field = &mut form.fields[form.active_field];
+                field.value.insert(byte_offset(&field.value, field.cursor_pos), c);
+                field.cursor_pos += 1;
+            }
+            KeyCode::Enter => {
+                let name = form.fields[0].value.trim().to_string();
+                let key = form.fields[1].value.trim().to_string();
+                let command = argv::split(&form.fields[2].value);
+                let desc_input = form.fields[3].value.trim().to_string();
+                let group_input = form.fields[4].value.trim().to_string();
+                let tags = parse_tags(&form.fields[5].value);
+                let cwd_input = form.fields[6].value.trim().to_string();
+                let env = parse_env_pairs(&form.fields[7].value);
+                let env_remove_input: Vec<String> = form.fields[8].value.split_whitespace().map(|s| s.to_string()).collect();
+                let env_clear = parse_yes_no(form.fields[9].value.trim());
+                let mode = parse_mode(form.fields[10].value.trim());
+
+                if name.is_empty() || key.is_empty() {
+                    form.error_message = Some("Name and Key cannot be empty.".to_string());
+                } else if let Err(e) = &command {
+                    form.error_message = Some(format!("Command: {}", e));
+                } else if command.as_ref().is_ok_and(|words| words.first().is_none_or(String::is_empty)) {
+                    form.error_message = Some("Command cannot be empty.".to_string());
+                } else if !cwd_input.is_empty() && launcher::expand_dir(&cwd_input).is_none() {
+                    form.error_message = Some("Working Dir must be absolute, ~ or $XDG_*.".to_string());
+                } else if let Err(e) = &env {
+                    form.error_message = Some(e.clone());
+                } else if let Err(e) = &env_clear {
+                    form.error_message = Some(format!("Clear Env: {}", e));
+                } else if let Err(e) = &mode {
+                    form.error_message = Some(e.clone());
+                } else {
+                    // The app being edited may keep its own key
+                    let key_conflict = self.config.apps.iter().enumerate().find(|(idx, app)| {
+                        let is_current = form.is_edit && current == Some(*idx);
+                        !is_current && config::keys_overlap(&app.key, &key)
+                    });
+
+                    if let Some((_, other)) = key_conflict {
+                        form.error_message = Some(config::key_conflict_message(&key, other));
+                    } else if let Some(clash) = keymap.hotkey_clash(&key) {
+                        form.error_message = Some(clash);
+                    } else {
+                        let mut words = command.unwrap_or_default().into_iter();
+                        let cmd = words.next().unwrap_or_default();
+                        let args: Vec<String> = words.collect();
+                        let args = if args.is_empty() { None } else { Some(args) };
+
+                        let description = if desc_input.is_empty() {
+                            None
+                        } else {
+                            Some(desc_input)
+                        };
+                        let group = if group_input.is_empty() { None } else { Some(group_input) };
+                        let cwd = if cwd_input.is_empty() { None } else { Some(cwd_input) };
+                        let env = env.unwrap_or_default();
+                        let env_clear = env_clear.unwrap_or_default();
+                        let env_remove = if env_remove_input.is_empty() { None } else { Some(env_remove_input) };
+                        let mode = mode.unwrap_or_default();
+
+                        // An edited app keeps its id so history and selection follow it
+                        let id = match current.filter(|_| form.is_edit) {
+                            Some(idx) => self.config.apps[idx].id.clone(),
+                            None => config::new_app_id(&self.config.apps, &mut self.id_seed),
+                        };
+                        let app = App {
+                            id,
+                            name,
+                            key,
+                            cmd,
+                            args,
+                            description,
+                            group,
+                            tags,
+                            cwd,
+                            env_clear,
+                            env_remove,
+                            env,
+                            mode,
+                        };
+                        if !form.is_edit {
+                            self.config.apps.push(app);
+                        } else if let Some(idx) = current {
+                            self.config.apps[idx] = app;
+                        }
+                        // The form stays open until the save went through
+                        effects.push(Effect::Save(self.config.clone()));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_list_key(&mut self, key_event: KeyEvent, now: Instant, effects: &mut Vec<Effect>) {
+        let keymap = Keymap::from_config(&self.config).unwrap_or_default();
+        let query = query::parse(&self.search_query);
+        let (ranked, rows) = list_rows(&self.config, &self.history, &query, &self.collapsed, self.unix_time);
+        let selected_app = match rows.get(self.selected) {
+            Some(ListRow::App(i)) => Some(ranked[*i].0),
+            _ => None,
+        };
+        // Group of the selected header or app
+        let selected_group: Option<&str> = match rows.get(self.selected) {
+            Some(ListRow::Header { group, .. }) => Some(group),
+            Some(ListRow::App(i)) => ranked[*i].0.group(),
+            None => None,
+        };
+        let max_items = self.layout().max_items;
+
+        // Anything but another plain character abandons a pending sequence
+        let plain_char = match (key_event.code, key_event.modifiers) {
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => Some(c),
+            _ => None,
+        };
+        let in_sequence = !self.chord.is_empty() || self.leader_pending;
+        if in_sequence && plain_char.is_none() {
+            self.chord.clear();
+            self.leader_pending = false;
+            return;
+        }
+        // Without vim mode plain characters type hotkeys; with it only after the leader
+        let hotkey_char = plain_char.filter(|_| !self.command_active && (in_sequence || !keymap.vim_mode()));
+        let mut motion = None;
+        // Mid-sequence every character belongs to the hotkey
+        let action = if self.command_active {
+            match key_event.code {
+                KeyCode::Esc => {
+                    self.command_active = false;
+                    None
+                }
+                KeyCode::Enter => {
+                    self.command_active = false;
+                    match keys::parse_command(&std::mem::take(&mut self.command_line)) {
+                        Ok(keys::Command::Action(action)) => Some(action),
+                        Ok(keys::Command::Motion(m)) => {
+                            motion = Some(m);
+                            None
+                        }
+                        Ok(keys::Command::ClearSearch) => {
+                            self.search_query.clear();
+                            self.search_cursor_pos = 0;
+                            None
+                        }
+                        Err(e) => {
+                            self.toast = Some(Toast::new(e, true, now));
+                            None
+                        }
+                    }
+                }
+                // Backspace on an empty line leaves it, as in vim
+                KeyCode::Backspace => {
+                    self.command_active = self.command_line.pop().is_some();
+                    None
+                }
+                KeyCode::Char(c) => {
+                    self.command_line.push(c);
+                    None
+                }
+                _ => None,
+            }
+        } else if in_sequence {
+            None
+        } else {
+            match keymap.vim_step(&key_event, &mut self.vim_pending) {
+                Some(VimStep::Action(action)) => Some(action),
+                Some(VimStep::Motion(m)) => {
+                    motion = Some(m);
+                    None
+                }
+                Some(VimStep::Command) => {
+                    self.command_active = true;
+                    self.command_line.clear();
+                    None
+                }
+                Some(VimStep::Leader) => {
+                    self.leader_pending = true;
+                    self.chord_started = now;
+                    None
+                }
+                Some(VimStep::Pending) => None,
+                None => keymap.action(&key_event),
+            }
+        };
+        let last_row = rows.len().saturating_sub(1);
+        let half_page = (max_items / 2).max(1);
+        // n/N step through the apps the search kept, wrapping around
+        let matches: Vec<usize> = if query.is_empty() { Vec::new() } else {
+            rows.iter().enumerate().filter(|(_, r)| matches!(r, ListRow::App(_))).map(|(i, _)| i).collect()
+        };
+        let motion = motion.or(match action {
+            Some(Action::PageUp) => Some(Motion::PageUp),
+            Some(Action::PageDown) => Some(Motion::PageDown),
+            Some(Action::First) => Some(Motion::Top),
+            Some(Action::Last) => Some(Motion::Bottom),
+            _ => None,
+        });
+        let page = max_items.saturating_sub(1).max(1);
+        match motion {
+            Some(Motion::PageUp) => self.selected = self.selected.saturating_sub(page),
+            Some(Motion::PageDown) => self.selected = (self.selected + page).min(last_row),
+            Some(Motion::Top) => self.selected = 0,
+            Some(Motion::Bottom) => self.selected = last_row,
+            Some(Motion::Line(line)) => self.selected = (line - 1).min(last_row),
+            Some(Motion::HalfPageDown) => self.selected = (self.selected + half_page).min(last_row),
+            Some(Motion::HalfPageUp) => self.selected = self.selected.saturating_sub(half_page),
+            Some(Motion::NextMatch) => {
+                if let Some(&next) = matches.iter().find(|&&i| i > self.selected).or(matches.first()) {
+                    self.selected = next;
+                }
+            }
+            Some(Motion::PrevMatch) => {
+                if let Some(&prev) = matches.iter().rev().find(|&&i| i < self.selected).or(matches.last()) {
+                    self.selected = prev;
+                }
+            }
+            None => {}
+        }
+        match action {
+            Some(Action::Quit) => effects.push(Effect::Quit),
+            Some(Action::Search) => {
+                self.search_active = true;
+                self.search_cursor_pos = self.search_query.chars().count();
+            }
+            Some(Action::Delete) if selected_app.is_some() => {
+                self.modal_state = ModalState::DeleteConfirm;
+            }
+            Some(Action::Add) => {
+                // New apps land in the group currently selected
+                let new_group = selected_group.unwrap_or_default().to_string();
+                self.modal_state = ModalState::Form;
+                self.active_form = Some(FormState {
+                    title: "Add New Application",
+                    fields: vec![
+                        FormField { label: "Name", value: String::new(), cursor_pos: 0 },
+                        FormField { label: "Hotkey", value: String::new(), cursor_pos: 0 },
+                        FormField { label: "Command", value: String::new(), cursor_pos: 0 },
+                        FormField { label: "Description", value: String::new(), cursor_pos: 0 },
+                        FormField { label: "Group", value: new_group.clone(), cursor_pos: new_group.chars().count() },
+                        FormField { label: "Tags", value: String::new(), cursor_pos: 0 },
+                        FormField { label: "Working Dir", value: String::new(), cursor_pos: 0 },
+                        FormField { label: "Env", value: String::new(), cursor_pos: 0 },
+                        FormField { label: "Env Remove", value: String::new(), cursor_pos: 0 },
+                        FormField { label: "Clear Env", value: String::new(), cursor_pos: 0 },
+                        FormField { label: "Mode", value: String::new(), cursor_pos: 0 },
+                    ],
+                    active_field: 0,
+                    error_message: None,
+                    is_edit: false,
+                });
+            }
+            Some(Action::Edit) if let Some(app) = selected_app => {
+                // Quoted so that arguments with spaces survive the round trip
+                let full_cmd_str = argv::join(std::iter::once(&app.cmd).chain(app.args.iter().flatten()));
+                let current_desc = app.description.clone().unwrap_or_default();
+                let current_group = app.group.clone().unwrap_or_default();
+                let current_tags = app.tags.join(" ");
+                let current_cwd = app.cwd.clone().unwrap_or_default();
+                let current_env = argv::join(app.env.iter().flatten().map(|(k, v)| format!("{}={}", k, v)));
+                let current_env_remove = app.env_remove.clone().unwrap_or_default().join(" ");
+                let current_env_clear = if app.env_clear == Some(true) { "yes".to_string() } else { String::new() };
+                let current_mode = app.mode.map(LaunchMode::name).unwrap_or_default();
+
+                self.modal_state = ModalState::Form;
+                self.active_form = Some(FormState {
+                    title: "Edit Application",
+                    fields: vec![
+                        FormField { label: "Name", value: app.name.clone(), cursor_pos: app.name.chars().count() },
+                        FormField { label: "Hotkey", value: app.key.clone(), cursor_pos: app.key.chars().count() },
+                        FormField { label: "Command", value: full_cmd_str.clone(), cursor_pos: full_cmd_str.chars().count() },
+                        FormField { label: "Description", value: current_desc.clone(), cursor_pos: current_desc.chars().count() },
+                        FormField { label: "Group", value: current_group.clone(), cursor_pos: current_group.chars().count() },
+                        FormField { label: "Tags", value: current_tags.clone(), cursor_pos: current_tags.chars().count() },
+                        FormField { label: "Working Dir", value: current_cwd.clone(), cursor_pos: current_cwd.chars().count() },
+                        FormField { label: "Env", value: current_env.clone(), cursor_pos: current_env.chars().count() },
+                        FormField { label: "Env Remove", value: current_env_remove.clone(), cursor_pos: current_env_remove.chars().count() },
+                        FormField { label: "Clear Env", value: current_env_clear.clone(), cursor_pos: current_env_clear.chars().count() },
+                        FormField { label: "Mode", value: current_mode.to_string(), cursor_pos: current_mode.chars().count() },
+                    ],
+                    active_field: 0,
+                    error_message: None,
+                    is_edit: true,
+                });
+            }
+            Some(Action::Theme) => {
+                self.modal_state = ModalState::ThemeSelect;
+                // Attempt to match selected_theme_idx to currently set theme
+                if let Some(ref current_theme) = self.config.theme
+                    && let Some(ref current_accent) = current_theme.accent_color
+                    && let Some(pos) = PREDEFINED_THEMES.iter().position(|t| t.accent_color == current_accent)
+                {
+                    self.selected_theme_idx = pos;
+                }
+            }
+            // Moving past either end wraps around
+            Some(Action::MoveUp) => {
+                self.selected = self.selected.checked_sub(1).unwrap_or(last_row);
+            }
+            Some(Action::MoveDown) => {
+                self.selected = if self.selected < last_row { self.selected + 1 } else { 0 };
+            }
+            // Fold the selected group; folding from an app moves to its header
+            Some(Action::CollapseGroup | Action::ExpandGroup | Action::Launch) if let Some(group) = selected_group
+                && (selected_app.is_none() || action == Some(Action::CollapseGroup))
+                && query.is_empty() =>
+            {
+                let fold = match action {
+                    Some(Action::CollapseGroup) => true,
+                    Some(Action::ExpandGroup) => false,
+                    _ => !self.collapsed.contains(group),
+                };
+                if fold {
+                    self.collapsed.insert(group.to_string());
+                } else {
+                    self.collapsed.remove(group);
+                }
+                self.reselect = Some(RowId::Group(group.to_string()));
+            }
+            // Jump between group headers, wrapping around
+            Some(Action::NextGroup | Action::PrevGroup) => {
+                let headers: Vec<usize> = rows.iter().enumerate().filter(|(_, r)| matches!(r, ListRow::Header { .. })).map(|(i, _)| i).collect();
+                let target = if action == Some(Action::NextGroup) {
+                    headers.iter().find(|&&i| i > self.selected).or(headers.first())
+                } else {
+                    headers.iter().rev().find(|&&i| i < self.selected).or(headers.last())
+                };
+                if let Some(&target) = target {
+                    self.selected = target;
+                }
+            }
+            // Collapse every group, or expand them all if already collapsed
+            Some(Action::FoldAll) => {
+                let all_groups: Vec<String> = rows.iter().filter_map(|r| match r {
+                    ListRow::Header { group, .. } => Some(group.to_string()),
+                    _ => None,
+                }).collect();
+                let expand = all_groups.iter().all(|g| self.collapsed.contains(g));
+                self.reselect = match (selected_app, selected_group) {
+                    (Some(app), None) => Some(RowId::App(app.id.clone())),
+                    (_, Some(group)) => Some(RowId::Group(group.to_string())),
+                    (None, None) => None,
+                };
+                if expand {
+                    self.collapsed.clear();
+                } else {
+                    self.collapsed.extend(all_groups);
+                }
+            }
+            Some(Action::Launch) if let Some(app) = selected_app => effects.push(Effect::Launch(app.clone())),
+            None if let Some(c) = hotkey_char => {
+                let mut typed = std::mem::take(&mut self.chord);
+                typed.push(c);
+                match chord_step(&self.config.apps, &typed) {
+                    ChordStep::Launch(app) => {
+                        self.leader_pending = false;
+                        effects.push(Effect::Launch(app.clone()));
+                    }
+                    ChordStep::Pending => {
+                        self.chord = typed;
+                        self.chord_started = now;
+                    }
+                    ChordStep::NoMatch => self.leader_pending = false,
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Unix time the test states start at
+    const START: u64 = 1_700_000_000;
+
+    fn app(id: &str, name: &str, key: &str) -> App {
+        App { id: id.to_string(), name: name.to_string(), key: key.to_string(), cmd: format!("/usr/bin/{}", name.to_lowercase()), ..App::default() }
+    }
+
+    fn state(apps: Vec<App>) -> (State, Instant) {
+        let now = Instant::now();
+        let config = Config { apps, ..Config::default() };
+        (State::new(config, None, History::default(), (100, 30), now, START, 42), now)
+    }
+
+    fn key(code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn ctrl(c: char) -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL))
+    }
+
+    fn text(s: &str) -> Vec<Event> {
+        s.chars().map(|c| key(KeyCode::Char(c))).collect()
+    }
+
+    // Feeds the events in order, all at `now`, and collects the effects
+    fn feed(mut state: State, events: impl IntoIterator<Item = Event>, now: Instant) -> (State, Vec<Effect>) {
+        let mut all = Vec::new();
+        for event in events {
+            let (next, effects) = update(state, event, now);
+            state = next;
+            all.extend(effects);
+        }
+        (state, all)
+    }
+
+    fn saved(effects: &[Effect]) -> &Config {
+        match effects {
+            [Effect::Save(config)] => config,
+            other => panic!("expected one save, got {:?}", other),
+        }
+    }
+
+    fn launched(effects: &[Effect]) -> &str {
+        match effects {
+            [Effect::Launch(app)] => &app.name,
+            other => panic!("expected one launch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn add_via_form_saves_the_new_app() {
+        let (state, now) = state(vec![app("a1", "Firefox", "f")]);
+        let mut events = vec![ctrl('a')];
+        events.extend(text("Vim"));
+        events.push(key(KeyCode::Tab));
+        events.extend(text("v"));
+        events.push(key(KeyCode::Tab));
+        events.extend(text("vim -p"));
+        events.push(key(KeyCode::Enter));
+        let (state, effects) = feed(state, events, now);
+
+        let config = saved(&effects).clone();
+        let added = &config.apps[1];
+        assert_eq!((added.name.as_str(), added.key.as_str(), added.cmd.as_str()), ("Vim", "v", "vim"));
+        assert_eq!(added.args, Some(vec!["-p".to_string()]));
+        assert!(!added.id.is_empty() && added.id != "a1");
+        // Still open until the save is reported back
+        assert_eq!(state.modal_state, ModalState::Form);
+        let (state, effects) = update(state, Event::Saved(Ok(config)), now);
+        assert!(effects.is_empty());
+        assert_eq!(state.modal_state, ModalState::None);
+        assert_eq!(state.config.apps.len(), 2);
+    }
+
+    #[test]
+    fn new_app_ids_follow_the_seed() {
+        let add = |state| {
+            let mut events = vec![ctrl('a')];
+            events.extend(text("Vim"));
+            events.push(key(KeyCode::Tab));
+            events.extend(text("v"));
+            events.push(key(KeyCode::Tab));
+            events.extend(text("vim"));
+            events.push(key(KeyCode::Enter));
+            let (_, effects) = feed(state, events, Instant::now());
+            saved(&effects).apps[0].id.clone()
+        };
+        assert_eq!(add(state(vec![]).0), add(state(vec![]).0));
+    }
+
+    #[test]
+    fn edit_keeps_the_id() {
+        let (state, now) = state(vec![app("a1", "Firefox", "f"), app("b2", "Vim", "v")]);
+        let mut events = vec![key(KeyCode::Down), ctrl('e')];
+        events.extend(text("x"));
+        events.push(key(KeyCode::Enter));
+        let (_, effects) = feed(state, events, now);
+
+        let edited = &saved(&effects).apps[1];
+        assert_eq!(edited.name, "Vimx");
+        assert_eq!(edited.id, "b2");
+    }
+
+    #[test]
+    fn delete_asks_first() {
+        let (state, now) = state(vec![app("a1", "Firefox", "f"), app("b2", "Vim", "v")]);
+        let (state, effects) = feed(state, [ctrl('d'), key(KeyCode::Char('n'))], now);
+        assert!(effects.is_empty());
+        assert_eq!(state.modal_state, ModalState::None);
+        assert_eq!(state.config.apps.len(), 2);
+
+        let (state, effects) = feed(state, [ctrl('d'), key(KeyCode::Esc)], now);
+        assert!(effects.is_empty());
+        assert_eq!(state.modal_state, ModalState::None);
+
+        let (state, effects) = feed(state, [ctrl('d'), key(KeyCode::Char('y'))], now);
+        let ids: Vec<&str> = saved(&effects).apps.iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(ids, ["b2"]);
+        assert_eq!(state.modal_state, ModalState::None);
+    }
+
+    #[test]
+    fn theme_select_saves_the_picked_theme() {
+        let (state, now) = state(vec![]);
+        let (state, effects) = feed(state, [ctrl('t'), key(KeyCode::Down), key(KeyCode::Down), key(KeyCode::Up)], now);
+        assert!(effects.is_empty());
+        assert_eq!(state.modal_state, ModalState::ThemeSelect);
+
+        let (state, effects) = update(state, key(KeyCode::Enter), now);
+        let theme = saved(&effects).theme.clone().expect("theme set");
+        assert_eq!(theme.border_color.as_deref(), Some(PREDEFINED_THEMES[1].border_color));
+        assert_eq!(theme.accent_color.as_deref(), Some(PREDEFINED_THEMES[1].accent_color));
+        assert_eq!(state.modal_state, ModalState::None);
+
+        let (state, effects) = feed(state, [ctrl('t'), key(KeyCode::Esc)], now);
+        assert!(effects.is_empty());
+        assert_eq!(state.modal_state, ModalState::None);
+    }
+
+    #[test]
+    fn search_then_enter_launches_the_first_match() {
+        let (state, now) = state(vec![app("a1", "Firefox", "f"), app("b2", "Vim", "v"), app("c3", "Vivaldi", "w")]);
+        let mut events = vec![key(KeyCode::Char('/'))];
+        events.extend(text("vi"));
+        // The first Enter only leaves the search field
+        events.push(key(KeyCode::Enter));
+        let (state, effects) = feed(state, events, now);
+        assert!(effects.is_empty());
+        assert!(!state.search_active);
+        let first = state.list().0[0].0.name.clone();
+        assert_eq!(first, "Vim");
+
+        let (_, effects) = update(state, key(KeyCode::Enter), now);
+        assert_eq!(launched(&effects), first);
+    }
+
+    #[test]
+    fn launches_are_recorded_at_the_event_time() {
+        let (state, now) = state(vec![app("a1", "Firefox", "f")]);
+        let later = now + Duration::from_secs(90);
+        let (state, effects) = update(state, Event::Launched { app: app("a1", "Firefox", "f"), code: Some(0) }, later);
+        assert!(matches!(effects[..], [Effect::SaveHistory]));
+        assert_eq!(state.history.launches[0].timestamp, START + 90);
+    }
+
+    #[test]
+    fn exec_launches_are_recorded_by_the_runner() {
+        // Only once the command resolves; an unresolvable one never runs
+        let (state, now) = state(vec![App { mode: Some(LaunchMode::Exec), ..app("a1", "Vim", "v") }]);
+        let (state, effects) = update(state, key(KeyCode::Enter), now);
+        assert_eq!(launched(&effects), "Vim");
+        assert!(state.history.launches.is_empty());
+    }
+
+    #[test]
+    fn search_edits_non_ascii_text() {
+        let (state, now) = state(vec![app("a1", "Café", "c")]);
+        let mut events = vec![key(KeyCode::Char('/'))];
+        events.extend(text("éa"));
+        events.extend([key(KeyCode::Left), key(KeyCode::Backspace), key(KeyCode::Char('ü')), key(KeyCode::Right), key(KeyCode::Char('ß'))]);
+        events.extend([key(KeyCode::Left), key(KeyCode::Left), key(KeyCode::Delete)]);
+        let (state, _) = feed(state, events, now);
+        assert_eq!(state.search_query, "üß");
+        assert_eq!(state.search_cursor_pos, 1);
+    }
+
+    #[test]
+    fn form_edits_non_ascii_text() {
+        let (state, now) = state(vec![app("a1", "Café", "c")]);
+        let mut events = vec![ctrl('e'), key(KeyCode::Left), key(KeyCode::Backspace)];
+        events.extend(text("fè"));
+        events.extend([key(KeyCode::Right), key(KeyCode::Char('!')), key(KeyCode::Enter)]);
+        let (_, effects) = feed(state, events, now);
+        assert_eq!(saved(&effects).apps[0].name, "Cafèé!");
+    }
+
+    #[test]
+    fn form_values_outgrow_the_input_box() {
+        let (state, now) = state(vec![]);
+        let cmd = format!("/usr/bin/{}", "x".repeat(FORM_INPUT_WIDTH));
+        let mut events = vec![ctrl('a')];
+        events.extend(text("Long"));
+        events.push(key(KeyCode::Tab));
+        events.extend(text("l"));
+        events.push(key(KeyCode::Tab));
+        events.extend(text(&cmd));
+        let (state, _) = feed(state, events, now);
+        let field = &state.active_form.as_ref().unwrap().fields[2];
+        assert_eq!(field.value, cmd);
+        assert_eq!(field.scroll(), cmd.len() + 1 - FORM_INPUT_WIDTH);
+    }
+
+    #[test]
+    fn chord_completes_with_the_next_key() {
+        let (state, now) = state(vec![app("a1", "Gimp", "g"), app("b2", "Glow", "gl")]);
+        let (state, effects) = update(state, key(KeyCode::Char('g')), now);
+        assert!(effects.is_empty());
+        assert_eq!(state.chord, "g");
+
+        let (state, effects) = update(state, key(KeyCode::Char('l')), now + Duration::from_millis(500));
+        assert_eq!(launched(&effects), "Glow");
+        assert!(state.chord.is_empty());
+    }
+
+    #[test]
+    fn chord_times_out_on_tick() {
+        let (state, now) = state(vec![app("a1", "Gimp", "g"), app("b2", "Glow", "gl")]);
+        let (state, _) = update(state, key(KeyCode::Char('g')), now);
+        let (state, effects) = update(state, Event::Tick, now + CHORD_TIMEOUT / 2);
+        assert!(effects.is_empty());
+        assert_eq!(state.chord, "g");
+
+        let (state, effects) = update(state, Event::Tick, now + CHORD_TIMEOUT);
+        assert_eq!(launched(&effects), "Gimp");
+        assert!(state.chord.is_empty());
+    }
+}
@@ -0,0 +1,74 @@
+// Shell-style words for the add/edit form.
+//
+// The Command field is typed like a shell command line: words are separated by
+// blanks and can be quoted ('single', "double") or escaped with a backslash.
+// Nothing is expanded and no shell ever runs; this only turns the text into the
+// `cmd` and `args` an app is started with, and back.
+
+// Splits a command line into words, POSIX-style but without any expansion
+pub fn split(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    // None between words; quotes start a word even if it stays empty
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("missing closing ' quote.".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        // Inside double quotes a backslash only escapes these, as in sh
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("missing closing \" quote.".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("missing closing \" quote.".to_string()),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => return Err("nothing to escape after the final \\.".to_string()),
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+// Quotes a word only if `split` would not read it back unchanged otherwise
+pub fn quote(word: &str) -> String {
+    let plain = |c: char| c.is_alphanumeric() || "-_./:=,+@%^".contains(c);
+    if !word.is_empty() && word.chars().all(plain) {
+        word.to_string()
+    } else if !word.contains('\'') {
+        format!("'{}'", word)
+    } else if !word.contains(['"', '\\', '$', '`']) {
+        format!("\"{}\"", word)
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+// The words as one line that `split` turns back into the same words
+pub fn join<S: AsRef<str>>(words: impl IntoIterator<Item = S>) -> String {
+    words.into_iter().map(|w| quote(w.as_ref())).collect::<Vec<_>>().join(" ")
+}
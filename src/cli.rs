@@ -0,0 +1,453 @@
+use crate::argv;
+use crate::config::{self, App, Config, LaunchMode};
+use crate::history::{self, History};
+use crate::keys::Keymap;
+use crate::launcher;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+const EXIT_OK: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+// Shell conventions: found but could not be executed / not found
+const EXIT_CANNOT_EXECUTE: i32 = 126;
+const EXIT_NOT_FOUND: i32 = 127;
+
+const USAGE: &str = "Usage: term-launcher [OPTIONS] [COMMAND]
+
+Without a command the interactive launcher is started.
+
+Options:
+  --config <path>               Use this config file
+  --profile <name>              Use profiles/<name>.toml in the config dir
+  The default is $TERM_LAUNCHER_CONFIG, else $XDG_CONFIG_HOME/term-launcher/config.toml
+  (~/.config/term-launcher/config.toml when XDG_CONFIG_HOME is unset).
+
+Commands:
+  list [--json]                 List configured applications
+  launch <name|key|id>          Launch an application (in the foreground unless its mode says otherwise)
+  add --name <name> --key <key> --cmd <cmd> [--description <text>] [--group <name>] [--tags <a,b>] [--cwd <dir>] [--mode <mode>] [-- args...]
+                                Add an application to the config
+  remove <name|key|id>          Remove an application from the config
+  validate                      Check the config for problems
+  migrate [--yes]               Move arguments written into `cmd` to `args` and store missing app ids, after confirmation
+  help                          Show this message
+
+Exit codes:
+  0 success, 1 failure, 2 usage error,
+  126 command could not be executed, 127 command not found or not allowed.
+  `launch` otherwise exits with the launched program's status.";
+
+struct Failure {
+    code: i32,
+    message: String,
+}
+
+fn fail(code: i32, message: impl Into<String>) -> Failure {
+    Failure { code, message: message.into() }
+}
+
+type CliResult = Result<i32, Failure>;
+
+#[derive(Debug, Default)]
+pub struct GlobalOptions {
+    pub config: Option<String>,
+    pub profile: Option<String>,
+}
+
+// Splits leading --config/--profile options from the subcommand and its arguments
+pub fn parse_global_options(args: &[String]) -> Result<(GlobalOptions, &[String]), String> {
+    let mut options = GlobalOptions::default();
+    let mut rest = args;
+    while let Some((arg, tail)) = rest.split_first() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        if flag != "--config" && flag != "--profile" {
+            break;
+        }
+        let (value, tail) = match (inline_value, tail.split_first()) {
+            (Some(value), _) => (value, tail),
+            (None, Some((value, tail))) => (value.clone(), tail),
+            (None, None) => return Err(format!("{} needs a value", flag)),
+        };
+        if flag == "--config" {
+            options.config = Some(value);
+        } else {
+            options.profile = Some(value);
+        }
+        rest = tail;
+    }
+    if options.config.is_some() && options.profile.is_some() {
+        return Err("--config and --profile cannot be combined".to_string());
+    }
+    Ok((options, rest))
+}
+
+pub fn run(args: &[String], config_path: &Path) -> i32 {
+    let (command, rest) = match args.split_first() {
+        Some((command, rest)) => (command.as_str(), rest),
+        None => ("help", &[][..]),
+    };
+    let result = match command {
+        "list" => list(rest, config_path),
+        "launch" => launch(rest, config_path),
+        "add" => add(rest, config_path),
+        "remove" => remove(rest, config_path),
+        "validate" => validate(rest, config_path),
+        "migrate" => migrate(rest, config_path),
+        "help" | "-h" | "--help" => {
+            println!("{}", USAGE);
+            Ok(EXIT_OK)
+        }
+        other => Err(fail(EXIT_USAGE, format!("Unknown command '{}'.\n\n{}", other, USAGE))),
+    };
+    match result {
+        Ok(code) => code,
+        Err(failure) => {
+            eprintln!("{}", failure.message);
+            failure.code
+        }
+    }
+}
+
+fn load(config_path: &Path) -> Result<Config, Failure> {
+    Config::load(config_path)
+        .map_err(|e| fail(EXIT_FAILURE, format!("Failed to load {}: {}", config_path.display(), e)))
+}
+
+fn save(config: &mut Config, config_path: &Path) -> Result<(), Failure> {
+    config
+        .save(config_path)
+        .map_err(|e| fail(EXIT_FAILURE, format!("Failed to save {}: {}", config_path.display(), e)))
+}
+
+fn expect_no_args(command: &str, args: &[String]) -> Result<(), Failure> {
+    match args.first() {
+        Some(arg) => Err(fail(EXIT_USAGE, format!("{}: unexpected argument '{}'", command, arg))),
+        None => Ok(()),
+    }
+}
+
+fn single_arg<'a>(command: &str, args: &'a [String]) -> Result<&'a str, Failure> {
+    match args {
+        [arg] => Ok(arg),
+        _ => Err(fail(EXIT_USAGE, format!("{}: expected exactly one <name|key|id> argument", command))),
+    }
+}
+
+// Exact name match wins over a key match, then the app's id; several apps with the same name are ambiguous
+fn find_app(config: &Config, query: &str) -> Result<usize, Failure> {
+    let by_name: Vec<usize> = config.apps.iter().enumerate().filter(|(_, a)| a.name == query).map(|(i, _)| i).collect();
+    match by_name.as_slice() {
+        [idx] => return Ok(*idx),
+        [] => {}
+        _ => return Err(fail(EXIT_FAILURE, format!("'{}' matches {} apps; use its key instead", query, by_name.len()))),
+    }
+    config
+        .apps
+        .iter()
+        .position(|a| a.key == query)
+        .or_else(|| config.apps.iter().position(|a| a.id == query))
+        .ok_or_else(|| fail(EXIT_FAILURE, format!("No app named, bound to or with id '{}'", query)))
+}
+
+#[derive(Serialize)]
+struct ListEntry<'a> {
+    #[serde(flatten)]
+    app: &'a App,
+    resolved: Option<String>,
+}
+
+fn list(args: &[String], config_path: &Path) -> CliResult {
+    let json = match args {
+        [] => false,
+        [flag] if flag == "--json" => true,
+        _ => return Err(fail(EXIT_USAGE, "list: only --json is accepted")),
+    };
+    let config = load(config_path)?;
+    let extra_dirs = config.extra_allowed_dirs();
+
+    if json {
+        let entries: Vec<ListEntry> = config
+            .apps
+            .iter()
+            .map(|app| ListEntry {
+                app,
+                resolved: launcher::resolve_app(app, extra_dirs).map(|(path, _)| path.display().to_string()),
+            })
+            .collect();
+        let out = serde_json::to_string_pretty(&entries).map_err(|e| fail(EXIT_FAILURE, e.to_string()))?;
+        println!("{}", out);
+        return Ok(EXIT_OK);
+    }
+
+    let key_width = config.apps.iter().map(|a| a.key.chars().count()).max().unwrap_or(0);
+    let name_width = config.apps.iter().map(|a| a.name.chars().count()).max().unwrap_or(0);
+    for app in &config.apps {
+        let line = argv::join(std::iter::once(&app.cmd).chain(app.args.iter().flatten()));
+        println!("{:key_width$}  {:name_width$}  {}", app.key, app.name, line);
+    }
+    Ok(EXIT_OK)
+}
+
+fn launch(args: &[String], config_path: &Path) -> CliResult {
+    let query = single_arg("launch", args)?;
+    let config = load(config_path)?;
+    let app = &config.apps[find_app(&config, query)?];
+    let extra_dirs = config.extra_allowed_dirs();
+
+    let Some((path, final_args)) = launcher::resolve_app(app, extra_dirs) else {
+        return Err(fail(EXIT_NOT_FOUND, launcher::refusal_message(&app.cmd, extra_dirs)));
+    };
+    let record = |code: Option<i32>| {
+        if let Some(history_path) = history::default_path() {
+            let mut history = History::load(&history_path);
+            history.record(app, code, history::now());
+            let _ = history.save(&history_path);
+        }
+    };
+    // Nothing runs after a successful exec, so it is recorded first
+    if app.mode == Some(LaunchMode::Exec) {
+        record(None);
+    }
+    let outcome = launcher::launch(app, &config, &path, &final_args)
+        .map_err(|e| fail(EXIT_CANNOT_EXECUTE, format!("Failed to launch command: {}", e)))?;
+    match outcome {
+        launcher::Outcome::Exited(status) => {
+            record(status.code());
+            Ok(status.code().unwrap_or(EXIT_FAILURE))
+        }
+        launcher::Outcome::Started => {
+            record(None);
+            Ok(EXIT_OK)
+        }
+    }
+}
+
+fn add(args: &[String], config_path: &Path) -> CliResult {
+    let mut flags: HashMap<&str, String> = HashMap::new();
+    let mut app_args = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            app_args.extend(iter.by_ref().cloned());
+            break;
+        }
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        let name = match flag {
+            "--name" | "--key" | "--cmd" | "--description" | "--group" | "--tags" | "--cwd" | "--mode" => &flag[2..],
+            _ => return Err(fail(EXIT_USAGE, format!("add: unknown argument '{}'", arg))),
+        };
+        let value = match inline_value.or_else(|| iter.next().cloned()) {
+            Some(value) => value,
+            None => return Err(fail(EXIT_USAGE, format!("add: {} needs a value", flag))),
+        };
+        flags.insert(name, value);
+    }
+
+    let mut take = |name: &str| flags.remove(name).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    let (Some(name), Some(key), Some(cmd)) = (take("name"), take("key"), take("cmd")) else {
+        return Err(fail(EXIT_USAGE, "add: --name, --key and --cmd are required"));
+    };
+    let description = take("description");
+    let group = take("group");
+    let tags: Vec<String> = take("tags")
+        .map(|tags| tags.split(',').map(|t| t.trim().trim_start_matches('#').to_string()).filter(|t| !t.is_empty()).collect())
+        .unwrap_or_default();
+    let cwd = take("cwd");
+    let mode = match take("mode") {
+        Some(name) => Some(LaunchMode::parse(&name).ok_or_else(|| fail(EXIT_USAGE, format!("add: --mode must be foreground, detach, exec or terminal, not '{}'", name)))?),
+        None => None,
+    };
+
+    let mut config = load(config_path)?;
+    if let Some(other) = config.apps.iter().find(|a| config::keys_overlap(&a.key, &key)) {
+        return Err(fail(EXIT_FAILURE, config::key_conflict_message(&key, other)));
+    }
+    if let Some(clash) = Keymap::from_config(&config).unwrap_or_default().hotkey_clash(&key) {
+        return Err(fail(EXIT_FAILURE, clash));
+    }
+    if let Some(dir) = &cwd
+        && launcher::expand_dir(dir).is_none()
+    {
+        return Err(fail(EXIT_FAILURE, format!("Working directory '{}' must be absolute, ~ or $XDG_*.", dir)));
+    }
+
+    let app = App {
+        id: config::new_app_id(&config.apps, &mut config::random_seed()),
+        name,
+        key,
+        cmd,
+        args: if app_args.is_empty() { None } else { Some(app_args) },
+        description,
+        group,
+        tags,
+        cwd,
+        mode,
+        ..Default::default()
+    };
+    // "htop -d 10" would be looked up as one program; its arguments belong after `--`
+    if let Some(words) = launcher::split_command(&app, config.extra_allowed_dirs()) {
+        return Err(fail(
+            EXIT_USAGE,
+            format!("add: --cmd '{}' includes arguments; pass them after `--`, e.g. --cmd {} -- {}", app.cmd, argv::quote(&words[0]), argv::join(&words[1..])),
+        ));
+    }
+    if launcher::resolve_app(&app, config.extra_allowed_dirs()).is_none() {
+        eprintln!("warning: '{}' does not resolve to an allowed executable yet", app.cmd);
+    }
+    println!("Added '{}' ({})", app.name, app.key);
+    config.apps.push(app);
+    save(&mut config, config_path)?;
+    Ok(EXIT_OK)
+}
+
+fn remove(args: &[String], config_path: &Path) -> CliResult {
+    let query = single_arg("remove", args)?;
+    let mut config = load(config_path)?;
+    let idx = find_app(&config, query)?;
+    let app = config.apps.remove(idx);
+    save(&mut config, config_path)?;
+    println!("Removed '{}' ({})", app.name, app.key);
+    Ok(EXIT_OK)
+}
+
+fn validate(args: &[String], config_path: &Path) -> CliResult {
+    expect_no_args("validate", args)?;
+    let config = load(config_path)?;
+    let extra_dirs = config.extra_allowed_dirs();
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+
+    for raw in extra_dirs {
+        if let Some(reason) = launcher::unusable_dir(raw) {
+            warnings.push(format!("[security] allowed_dirs entry '{}' is ignored: {}", raw, reason));
+        }
+    }
+
+    for (idx, app) in config.apps.iter().enumerate() {
+        let label = if app.name.is_empty() { format!("key '{}'", app.key) } else { format!("'{}'", app.name) };
+        if app.name.trim().is_empty() {
+            errors.push(format!("{}: name is empty", label));
+        }
+        if app.key.trim().is_empty() {
+            errors.push(format!("{}: key is empty", label));
+        } else if let Some(other) = config.apps[..idx].iter().find(|o| o.key == app.key) {
+            errors.push(format!("{}: key '{}' is already used by '{}'", label, app.key, other.name));
+        } else if let Some(other) = config.apps[..idx].iter().find(|o| !o.key.trim().is_empty() && config::keys_overlap(&o.key, &app.key)) {
+            errors.push(format!("{}: key '{}' overlaps '{}' of '{}'; one is a prefix of the other", label, app.key, other.key, other.name));
+        }
+        if app.cmd.trim().is_empty() {
+            errors.push(format!("{}: cmd is empty", label));
+        } else if launcher::split_command(app, extra_dirs).is_some() {
+            errors.push(format!("{}: cmd '{}' includes arguments; run `term-launcher migrate` to move them to args", label, app.cmd));
+        } else if launcher::resolve_app(app, extra_dirs).is_none() {
+            errors.push(format!("{}: command '{}' is not found in an allowed location", label, app.cmd));
+        }
+        if let Some(dir) = &app.cwd
+            && launcher::expand_dir(dir).is_none()
+        {
+            errors.push(format!("{}: cwd '{}' must be absolute, ~ or $XDG_*", label, dir));
+        }
+        if app.mode == Some(LaunchMode::Terminal) {
+            match &config.terminal {
+                None => errors.push(format!("{}: mode \"terminal\" needs a [terminal] table naming the emulator", label)),
+                Some(terminal) if launcher::resolve_command(&terminal.cmd, extra_dirs).is_none() => {
+                    errors.push(format!("{}: terminal '{}' is not found in an allowed location", label, terminal.cmd));
+                }
+                Some(_) if launcher::changes_env(app) && launcher::resolve_command("env", extra_dirs).is_none() => {
+                    errors.push(format!("{}: its environment is set through 'env', which is not found in an allowed location", label));
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    if let Err(key_errors) = Keymap::from_config(&config) {
+        errors.extend(key_errors);
+    }
+
+    for warning in &warnings {
+        eprintln!("warning: {}", warning);
+    }
+    for error in &errors {
+        eprintln!("error: {}", error);
+    }
+    if !errors.is_empty() {
+        return Err(fail(EXIT_FAILURE, format!("{}: {} problem(s) found", config_path.display(), errors.len())));
+    }
+    println!("{}: OK ({} apps)", config_path.display(), config.apps.len());
+    Ok(EXIT_OK)
+}
+
+// Rewrites apps whose `cmd` carries arguments (which launches used to split off
+// silently) into `cmd` + `args`, showing each change and asking first
+fn migrate(args: &[String], config_path: &Path) -> CliResult {
+    let assume_yes = match args {
+        [] => false,
+        [flag] if flag == "--yes" || flag == "-y" => true,
+        _ => return Err(fail(EXIT_USAGE, "migrate: only --yes is accepted")),
+    };
+    let mut config = load(config_path)?;
+    // Ids as written in the file; the ones `load` had to assign are stored too
+    let stored_ids: Vec<String> = std::fs::read_to_string(config_path)
+        .ok()
+        .and_then(|contents| toml::from_str::<Config>(&contents).ok())
+        .map(|stored| stored.apps.into_iter().map(|a| a.id).collect())
+        .unwrap_or_default();
+    let extra_dirs = config.extra_allowed_dirs().to_vec();
+    let mut changed = 0;
+    for (idx, app) in config.apps.iter_mut().enumerate() {
+        let new_id = stored_ids.get(idx) != Some(&app.id);
+        let split = launcher::split_command(app, &extra_dirs);
+        if !new_id && split.is_none() {
+            continue;
+        }
+
+        println!("{} ({}):", app.name, app.key);
+        if new_id {
+            println!("+id = {}", toml::Value::from(app.id.as_str()));
+        }
+        if let Some(words) = split {
+            let mut words = words.into_iter();
+            let cmd = words.next().unwrap_or_default();
+            let new_args: Vec<String> = words.chain(app.args.iter().flatten().cloned()).collect();
+            println!("-cmd = {}", toml::Value::from(app.cmd.as_str()));
+            if let Some(old_args) = &app.args {
+                println!("-args = {}", toml::Value::from(old_args.clone()));
+            }
+            println!("+cmd = {}", toml::Value::from(cmd.as_str()));
+            println!("+args = {}", toml::Value::from(new_args.clone()));
+            app.cmd = cmd;
+            app.args = Some(new_args);
+        }
+        println!();
+        changed += 1;
+    }
+    if changed == 0 {
+        println!("{}: nothing to migrate", config_path.display());
+        return Ok(EXIT_OK);
+    }
+    if !assume_yes && !confirm(&format!("Rewrite {} app(s) in {}? [y/N] ", changed, config_path.display())) {
+        return Err(fail(EXIT_FAILURE, "Migration cancelled; nothing was changed"));
+    }
+    config
+        .save_with_ids(config_path)
+        .map_err(|e| fail(EXIT_FAILURE, format!("Failed to save {}: {}", config_path.display(), e)))?;
+    println!("Migrated {} app(s)", changed);
+    Ok(EXIT_OK)
+}
+
+// Asks on stdout and reads the answer from stdin; anything but yes (or no input) declines
+fn confirm(prompt: &str) -> bool {
+    print!("{}", prompt);
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok() && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
@@ -1,18 +1,60 @@
+use crate::config_doc;
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::hash::{BuildHasher, DefaultHasher, Hash, Hasher, RandomState};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+pub const CONFIG_ENV_VAR: &str = "TERM_LAUNCHER_CONFIG";
+
+// Timestamped copies of previous versions kept in `backups/` next to the config
+const BACKUP_COUNT: usize = 5;
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
 pub struct App {
+    // Stable identity used by edits, history and selection; generated when missing
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
     pub name: String,
     pub cmd: String,
     pub key: String,
     // Optional explicit arguments to avoid shell parsing
     pub args: Option<Vec<String>>,
     pub description: Option<String>,
+    // Section the app is listed under in the TUI
+    pub group: Option<String>,
+    // Free-form labels, searchable with `#tag` in the search bar
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    // Working directory for the launched process (absolute, `~` or `$XDG_*`)
+    pub cwd: Option<String>,
+    // Environment adjustments, applied in order: clear, remove, then set
+    pub env_clear: Option<bool>,
+    pub env_remove: Option<Vec<String>>,
+    pub env: Option<BTreeMap<String, String>>,
+    // How the app is started; foreground when unset
+    pub mode: Option<LaunchMode>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LaunchMode {
+    // In the launcher's terminal, waiting for it to exit
+    #[default]
+    Foreground,
+    // In a session of its own without a terminal; the launcher keeps running
+    Detach,
+    // In place of the launcher process
+    Exec,
+    // In a new window of the `[terminal]` emulator
+    Terminal,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct Theme {
     pub accent_color: Option<String>,
     pub border_color: Option<String>,
@@ -20,15 +62,441 @@ pub struct Theme {
     pub dim_color: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Security {
+    // Extra directories commands may resolve from, on top of the built-in defaults.
+    // Supports a leading `~` and `$XDG_*` variables.
+    #[serde(default)]
+    pub allowed_dirs: Vec<String>,
+}
+
+// Terminal emulator for apps with `mode = "terminal"`: the program and the
+// arguments that precede the app's command, e.g. `alacritty -e`
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Terminal {
+    pub cmd: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Vim {
+    // Vim-style navigation; app hotkeys are then typed after the leader key
+    #[serde(default)]
+    pub enabled: bool,
+    pub leader: Option<String>,
+}
+
+// A `[keys]` entry: one combo, or several (an empty list unbinds the action)
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum KeyBinding {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
 pub struct Config {
+    #[serde(default)]
     pub apps: Vec<App>,
     pub theme: Option<Theme>,
+    pub security: Option<Security>,
+    // Action name -> key combo(s) overriding the default TUI bindings
+    pub keys: Option<BTreeMap<String, KeyBinding>>,
+    pub vim: Option<Vim>,
+    pub terminal: Option<Terminal>,
+    // What the file looked like when this config was loaded or last saved;
+    // None when there was no file
+    #[serde(skip)]
+    pub stamp: Option<FileStamp>,
+}
+
+// Fingerprint of the file contents, used to notice edits made by other programs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    len: usize,
+    hash: u64,
+}
+
+impl FileStamp {
+    pub fn of(contents: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        Self { len: contents.len(), hash: hasher.finish() }
+    }
+
+    // None when the file does not exist
+    pub fn read(path: &Path) -> std::io::Result<Option<Self>> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(Some(Self::of(&contents))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+// Where in the config file parsing failed; line and column are 1-based
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    // The offending line, for pointing at the column
+    pub source_line: String,
+}
+
+impl ParseError {
+    fn new(path: &Path, contents: &str, err: toml::de::Error) -> Self {
+        let offset = err.span().map(|span| span.start).unwrap_or(0);
+        let before = contents.get(..offset).unwrap_or(contents);
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        Self {
+            path: path.to_path_buf(),
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message: err.message().trim().lines().collect::<Vec<_>>().join("; "),
+            source_line: contents[line_start..].lines().next().unwrap_or("").to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.path.display(), self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Extracts the parse location from an error returned by `Config::load`
+pub fn parse_error(err: &std::io::Error) -> Option<&ParseError> {
+    err.get_ref()?.downcast_ref::<ParseError>()
+}
+
+// The file changed on disk since it was loaded, so saving would clobber someone else's edits
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub path: PathBuf,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} was changed by another program since it was loaded", self.path.display())
+    }
+}
+
+impl std::error::Error for Conflict {}
+
+// Extracts the conflict from an error returned by `Config::save`
+pub fn conflict(err: &std::io::Error) -> Option<&Conflict> {
+    err.get_ref()?.downcast_ref::<Conflict>()
+}
+
+// Writes to a temporary file in the same directory, syncs it and renames it over
+// the target, so a crash leaves either the old or the new contents. Symlinks are
+// followed and the existing file's permissions are kept.
+fn write_atomic(path: &Path, contents: &str) -> std::io::Result<()> {
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let dir = target.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = dir.join(format!(".{}.{}.tmp", name, std::process::id()));
+
+    let result = (|| {
+        let mut file = File::create(&tmp)?;
+        file.write_all(contents.as_bytes())?;
+        if let Ok(meta) = fs::metadata(&target) {
+            file.set_permissions(meta.permissions())?;
+        }
+        file.sync_all()?;
+        fs::rename(&tmp, &target)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+        return result;
+    }
+    // Make the rename itself durable
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+pub fn backup_dir(path: &Path) -> PathBuf {
+    path.with_file_name("backups")
+}
+
+// Stores `contents` as `backups/<name>.<unix time in ms>` and drops all but the newest BACKUP_COUNT
+fn rotate_backups(path: &Path, contents: &str) -> std::io::Result<()> {
+    let dir = backup_dir(path);
+    fs::create_dir_all(&dir)?;
+    let prefix = format!("{}.", path.file_name().unwrap_or_default().to_string_lossy());
+    let mut millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+    // Saves within the same millisecond take the next free stamp instead of replacing a backup
+    while dir.join(format!("{}{}", prefix, millis)).exists() {
+        millis += 1;
+    }
+    write_atomic(&dir.join(format!("{}{}", prefix, millis)), contents)?;
+
+    let mut backups: Vec<(u128, PathBuf)> = fs::read_dir(&dir)?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let stamp = entry.file_name().to_str()?.strip_prefix(&prefix)?.parse().ok()?;
+            Some((stamp, entry.path()))
+        })
+        .collect();
+    backups.sort();
+    let excess = backups.len().saturating_sub(BACKUP_COUNT);
+    for (_, old) in backups.drain(..excess) {
+        let _ = fs::remove_file(old);
+    }
+    Ok(())
+}
+
+// Re-applies the changes made between `base` and `ours` on top of `theirs`, the
+// version some other program wrote. Edits to an app the other side also changed
+// or removed keep both versions.
+pub fn merge(base: &Config, ours: &Config, mut theirs: Config, id_seed: &mut u64) -> Config {
+    for app in &ours.apps {
+        let old = base.apps.iter().find(|a| a.id == app.id);
+        if old == Some(app) {
+            continue;
+        }
+        match old.and_then(|old| theirs.apps.iter().position(|a| a == old)) {
+            Some(pos) => theirs.apps[pos] = app.clone(),
+            None => {
+                // Both versions of an app edited on both sides need their own id
+                let mut app = app.clone();
+                if theirs.apps.iter().any(|a| a.id == app.id) {
+                    app.id = new_app_id(&theirs.apps, id_seed);
+                }
+                theirs.apps.push(app);
+            }
+        }
+    }
+    for old in base.apps.iter().filter(|old| !ours.apps.iter().any(|a| a.id == old.id)) {
+        theirs.apps.retain(|a| a != old);
+    }
+    if ours.theme != base.theme {
+        theirs.theme = ours.theme.clone();
+    }
+    if ours.security != base.security {
+        theirs.security = ours.security.clone();
+    }
+    if ours.keys != base.keys {
+        theirs.keys = ours.keys.clone();
+    }
+    if ours.vim != base.vim {
+        theirs.vim = ours.vim.clone();
+    }
+    if ours.terminal != base.terminal {
+        theirs.terminal = ours.terminal.clone();
+    }
+    theirs
+}
+
+// Copy of the most recent config that loaded or saved cleanly
+pub fn last_good_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+// Refreshes the last good copy from a config file that just loaded cleanly
+pub fn keep_last_good(path: &Path) -> std::io::Result<()> {
+    let contents = fs::read_to_string(path)?;
+    let backup = last_good_path(path);
+    if fs::read_to_string(&backup).ok().as_deref() != Some(contents.as_str()) {
+        write_atomic(&backup, &contents)?;
+    }
+    Ok(())
+}
+
+// Id from an app's name and key, unique among `apps`
+fn derived_app_id(app: &App, apps: &[App]) -> String {
+    (0u32..)
+        .map(|n| short_id(app.name.bytes().chain([0]).chain(app.key.bytes()).chain(n.to_le_bytes())))
+        .find(|id| !apps.iter().any(|a| a.id == *id))
+        .unwrap_or_default()
+}
+
+// Moves the broken file aside as `<name>.broken` and puts the last good copy back
+pub fn restore_last_good(path: &Path) -> std::io::Result<()> {
+    let backup = last_good_path(path);
+    if !backup.exists() {
+        return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "no backup available"));
+    }
+    let mut broken = path.file_name().unwrap_or_default().to_os_string();
+    broken.push(".broken");
+    if path.exists() {
+        fs::rename(path, path.with_file_name(broken))?;
+    }
+    write_atomic(path, &fs::read_to_string(backup)?)
+}
+
+// Short id for a new app, unique among `apps`. Each call advances `seed`, so the
+// ids given out depend only on where the seed started (see `random_seed`)
+pub fn new_app_id(apps: &[App], seed: &mut u64) -> String {
+    loop {
+        *seed = seed.wrapping_add(1);
+        let id = short_id(seed.to_le_bytes());
+        if !apps.iter().any(|a| a.id == id) {
+            return id;
+        }
+    }
+}
+
+// A starting point for `new_app_id` that differs from run to run
+pub fn random_seed() -> u64 {
+    // Every RandomState is seeded differently
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    RandomState::new().hash_one(nanos)
+}
+
+// Eight hex digits hashing `bytes` (FNV-1a, so the same in every build)
+fn short_id(bytes: impl IntoIterator<Item = u8>) -> String {
+    let hash = bytes.into_iter().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+    format!("{:08x}", (hash ^ (hash >> 32)) as u32)
+}
+
+// Keys are typed as sequences, so a key that begins another one (or equals it)
+// would shadow it
+pub fn keys_overlap(a: &str, b: &str) -> bool {
+    a.starts_with(b) || b.starts_with(a)
+}
+
+// Message for a key that overlaps an existing app's key
+pub fn key_conflict_message(key: &str, other: &App) -> String {
+    if key == other.key {
+        format!("Key '{}' is already in use.", key)
+    } else if other.key.starts_with(key) {
+        format!("Key '{}' is a prefix of '{}' ({}).", key, other.key, other.name)
+    } else {
+        format!("Key '{}' starts with '{}' ({}).", key, other.key, other.name)
+    }
+}
+
+impl LaunchMode {
+    pub const ALL: [Self; 4] = [Self::Foreground, Self::Detach, Self::Exec, Self::Terminal];
+
+    // As written in the config
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Foreground => "foreground",
+            Self::Detach => "detach",
+            Self::Exec => "exec",
+            Self::Terminal => "terminal",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+}
+
+impl App {
+    // The group name, treating a blank one as no group
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_deref().map(str::trim).filter(|g| !g.is_empty())
+    }
 }
 
 impl Config {
-    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
-        let toml_string = toml::to_string(self).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-        fs::write(path, toml_string)
+    // A missing file is an empty config; a malformed one is an InvalidData error
+    // carrying a `ParseError`
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(path)?;
+        let mut config: Self = toml::from_str(&contents).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, ParseError::new(path, &contents, e))
+        })?;
+        config.stamp = Some(FileStamp::of(&contents));
+        // Only in memory: the ids reach the file with the next save (or `migrate`)
+        config.assign_ids();
+        Ok(config)
+    }
+
+    // Gives apps without an id, or with one already taken further up, one derived
+    // from the app, so it comes out the same on every load until it is saved
+    pub fn assign_ids(&mut self) {
+        for idx in 0..self.apps.len() {
+            let id = &self.apps[idx].id;
+            if id.is_empty() || self.apps[..idx].iter().any(|a| a.id == *id) {
+                self.apps[idx].id = derived_app_id(&self.apps[idx], &self.apps);
+            }
+        }
+    }
+
+    pub fn extra_allowed_dirs(&self) -> &[String] {
+        self.security.as_ref().map(|s| s.allowed_dirs.as_slice()).unwrap_or(&[])
+    }
+
+    // Refuses with a `Conflict` error if the file no longer matches `stamp`; set the
+    // stamp to `FileStamp::read` of the current file to overwrite anyway
+    pub fn save<P: AsRef<Path>>(&mut self, path: P) -> std::io::Result<()> {
+        self.write(path.as_ref(), false)
+    }
+
+    // Like `save`, but also stores the ids `load` assigned to apps written without one
+    pub fn save_with_ids<P: AsRef<Path>>(&mut self, path: P) -> std::io::Result<()> {
+        self.write(path.as_ref(), true)
+    }
+
+    fn write(&mut self, path: &Path, store_ids: bool) -> std::io::Result<()> {
+        let existing = match fs::read_to_string(path) {
+            Ok(contents) => Some(contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        if existing.as_deref().map(FileStamp::of) != self.stamp {
+            return Err(std::io::Error::other(Conflict { path: path.to_path_buf() }));
+        }
+
+        // Only the entries that changed are rewritten; comments and layout elsewhere survive
+        let toml_string = config_doc::render(existing.as_deref().unwrap_or_default(), self, store_ids).map_err(std::io::Error::other)?;
+        if let Some(previous) = existing.filter(|prev| *prev != toml_string) {
+            let _ = rotate_backups(path, &previous);
+        }
+        write_atomic(path, &toml_string)?;
+        self.stamp = Some(FileStamp::of(&toml_string));
+        let _ = write_atomic(&last_good_path(path), &toml_string);
+        Ok(())
+    }
+}
+
+// $XDG_CONFIG_HOME/term-launcher, falling back to ~/.config/term-launcher
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if Path::new(&dir).is_absolute() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok()?).join(".config"),
+    };
+    Some(base.join("term-launcher"))
+}
+
+// Precedence: explicit --config, then --profile, then $TERM_LAUNCHER_CONFIG,
+// then the default config.toml in the config dir.
+pub fn resolve_config_path(explicit: Option<&str>, profile: Option<&str>) -> Result<PathBuf, String> {
+    if let Some(path) = explicit {
+        return Ok(PathBuf::from(path));
+    }
+    if let Some(name) = profile {
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return Err(format!("Invalid profile name '{}'", name));
+        }
+        let dir = config_dir().ok_or("Cannot locate the config dir: neither XDG_CONFIG_HOME nor HOME is set")?;
+        return Ok(dir.join("profiles").join(format!("{}.toml", name)));
+    }
+    if let Ok(path) = env::var(CONFIG_ENV_VAR)
+        && !path.is_empty()
+    {
+        return Ok(PathBuf::from(path));
     }
+    let dir = config_dir().ok_or(format!(
+        "Cannot locate the config file: set {}, XDG_CONFIG_HOME or HOME, or pass --config",
+        CONFIG_ENV_VAR
+    ))?;
+    Ok(dir.join("config.toml"))
 }
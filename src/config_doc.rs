@@ -0,0 +1,196 @@
+// Format-preserving config rendering.
+//
+// Instead of re-serializing the whole config, the file on disk is parsed into a
+// toml_edit document and only the parts that differ from the in-memory config
+// are touched. Comments, blank lines, key order and quoting elsewhere in the
+// file stay byte-for-byte intact.
+
+use crate::config::{App, Config};
+use toml_edit::{ArrayOfTables, DocumentMut, InlineTable, Item, TableLike, Value};
+
+// With `store_ids`, ids the file lacks are written into every table (for `migrate`)
+pub fn render(existing: &str, config: &Config, store_ids: bool) -> Result<String, String> {
+    let fresh = || toml::to_string(config).map_err(|e| e.to_string());
+    let (Ok(mut doc), Ok(mut old)) = (existing.parse::<DocumentMut>(), toml::from_str::<Config>(existing)) else {
+        return fresh();
+    };
+    // The ids `Config::load` gave apps stored without one, so unchanged apps still
+    // match and only tables that are added or edited get their id written
+    if !store_ids {
+        old.assign_ids();
+    }
+    let new_doc = toml_edit::ser::to_document(config).map_err(|e| e.to_string())?;
+
+    for (key, new_item) in new_doc.iter() {
+        let Some(new_value) = new_item.as_value() else { continue };
+        if key == "apps" {
+            let new_apps: Vec<InlineTable> = new_value
+                .as_array()
+                .map(|arr| arr.iter().filter_map(|v| v.as_inline_table().cloned()).collect())
+                .unwrap_or_default();
+            match doc.get_mut("apps") {
+                Some(Item::ArrayOfTables(tables)) if tables.len() == old.apps.len() => {
+                    sync_apps(tables, &old.apps, &config.apps, &new_apps);
+                }
+                Some(item) if item.is_array_of_tables() => {
+                    *item = Item::ArrayOfTables(new_apps.into_iter().map(InlineTable::into_table).collect());
+                }
+                Some(item) => sync_item(item, new_value),
+                None if new_apps.is_empty() => {}
+                None => {
+                    doc.insert("apps", Item::ArrayOfTables(new_apps.into_iter().map(InlineTable::into_table).collect()));
+                }
+            }
+            continue;
+        }
+        match doc.get_mut(key) {
+            Some(item) => sync_item(item, new_value),
+            // New sections are written as regular [tables] rather than inline at the top
+            None => {
+                let item = match new_value.as_inline_table() {
+                    Some(inline) => Item::Table(inline.clone().into_table()),
+                    None => Item::Value(new_value.clone()),
+                };
+                doc.insert(key, item);
+            }
+        }
+    }
+
+    // Sections cleared in memory (e.g. an optional table set to None); keys the
+    // config does not know about are left alone
+    let old_doc = toml_edit::ser::to_document(&old).map_err(|e| e.to_string())?;
+    let stale: Vec<String> = old_doc.iter().map(|(k, _)| k.to_string()).filter(|k| k != "apps" && !new_doc.contains_key(k)).collect();
+    for key in stale {
+        doc.remove(&key);
+    }
+
+    Ok(doc.to_string())
+}
+
+// Matches apps that did not change (longest common subsequence), edits changed
+// ones in place, removes deleted ones and appends new ones at the end.
+fn sync_apps(tables: &mut ArrayOfTables, old: &[App], new: &[App], new_values: &[InlineTable]) {
+    let anchors = common_subsequence(old, new);
+    let mut removed = Vec::new();
+    let mut appended = Vec::new();
+    let (mut oi, mut ni) = (0, 0);
+
+    for (oa, na) in anchors.into_iter().chain(std::iter::once((old.len(), new.len()))) {
+        let common = (oa - oi).min(na - ni);
+        for k in 0..common {
+            if let Some(table) = tables.get_mut(oi + k) {
+                sync_table(table, &new_values[ni + k]);
+            }
+        }
+        removed.extend(oi + common..oa);
+        appended.extend(ni + common..na);
+        oi = oa + 1;
+        ni = na + 1;
+    }
+
+    for idx in removed.into_iter().rev() {
+        // Comments separated from the table by a blank line (e.g. a file header)
+        // are not part of the entry, so hand them to the next table along with
+        // the removed table's leading spacing
+        let prefix = tables.get(idx).and_then(|t| t.decor().prefix()).and_then(|p| p.as_str()).unwrap_or_default();
+        let detached = match prefix.rfind("\n\n") {
+            Some(end) => prefix[..end + 2].to_string(),
+            None => prefix.chars().take_while(|&c| c == '\n').collect(),
+        };
+        tables.remove(idx);
+        if let Some(next) = tables.get_mut(idx) {
+            let rest = next.decor().prefix().and_then(|p| p.as_str()).unwrap_or_default().trim_start_matches('\n').to_string();
+            next.decor_mut().set_prefix(detached + &rest);
+        }
+    }
+    for idx in appended {
+        tables.push(new_values[idx].clone().into_table());
+    }
+}
+
+fn common_subsequence(old: &[App], new: &[App]) -> Vec<(usize, usize)> {
+    let (n, m) = (old.len(), new.len());
+    let mut len = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            len[i][j] = if old[i] == new[j] { len[i + 1][j + 1] + 1 } else { len[i + 1][j].max(len[i][j + 1]) };
+        }
+    }
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i] == new[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if len[i + 1][j] >= len[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+fn sync_table(table: &mut dyn TableLike, new: &InlineTable) {
+    let stale: Vec<String> = table.iter().map(|(k, _)| k.to_string()).filter(|k| !new.contains_key(k)).collect();
+    for key in stale {
+        table.remove(&key);
+    }
+    for (key, value) in new.iter() {
+        match table.get_mut(key) {
+            Some(item) => sync_item(item, value),
+            None => {
+                table.insert(key, Item::Value(value.clone()));
+            }
+        }
+    }
+}
+
+fn sync_item(old: &mut Item, new: &Value) {
+    if same_item(old, new) {
+        return;
+    }
+    match old {
+        Item::Table(table) => match new.as_inline_table() {
+            Some(inline) => sync_table(table, inline),
+            None => *old = Item::Value(new.clone()),
+        },
+        Item::Value(Value::InlineTable(table)) if new.is_inline_table() => {
+            if let Some(inline) = new.as_inline_table() {
+                sync_table(table, inline);
+            }
+        }
+        // Keep the surrounding whitespace and any trailing comment
+        Item::Value(value) => {
+            let decor = value.decor().clone();
+            *value = new.clone();
+            *value.decor_mut() = decor;
+        }
+        _ => *old = Item::Value(new.clone()),
+    }
+}
+
+fn same_item(old: &Item, new: &Value) -> bool {
+    match (old.as_table_like(), new.as_inline_table()) {
+        (Some(table), Some(inline)) => {
+            table.len() == inline.len()
+                && table.iter().all(|(k, item)| inline.get(k).is_some_and(|v| same_item(item, v)))
+        }
+        (None, None) => old.as_value().is_some_and(|v| same_value(v, new)),
+        _ => false,
+    }
+}
+
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::String(x), Value::String(y)) => x.value() == y.value(),
+        (Value::Integer(x), Value::Integer(y)) => x.value() == y.value(),
+        (Value::Float(x), Value::Float(y)) => x.value() == y.value(),
+        (Value::Boolean(x), Value::Boolean(y)) => x.value() == y.value(),
+        (Value::Datetime(x), Value::Datetime(y)) => x.value() == y.value(),
+        (Value::Array(x), Value::Array(y)) => x.len() == y.len() && x.iter().zip(y.iter()).all(|(a, b)| same_value(a, b)),
+        (Value::InlineTable(x), Value::InlineTable(_)) => same_item(&Item::Value(Value::InlineTable(x.clone())), b),
+        _ => false,
+    }
+}
@@ -0,0 +1,112 @@
+// Subsequence fuzzy matcher used by the search bar.
+//
+// Every query character must appear in the candidate in order (case-insensitive).
+// Among all possible alignments the highest scoring one is kept, where matches
+// on word starts, camelCase humps and consecutive runs are rewarded and gaps
+// between matched characters are penalized.
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_BOUNDARY: i64 = 10;
+const BONUS_CAMEL: i64 = 8;
+const BONUS_CONSECUTIVE: i64 = 12;
+const BONUS_FIRST_CHAR: i64 = 6;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP_EXTENSION: i64 = 1;
+
+#[derive(Debug, Clone, Default)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    // Char indices (not byte offsets) into the candidate
+    pub positions: Vec<usize>,
+}
+
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '_' | '-' | '.' | '/' | ':' | '(' | '[' | '#')
+}
+
+fn position_bonus(chars: &[char], idx: usize) -> i64 {
+    if idx == 0 {
+        return BONUS_BOUNDARY + BONUS_FIRST_CHAR;
+    }
+    let prev = chars[idx - 1];
+    let cur = chars[idx];
+    if is_separator(prev) {
+        BONUS_BOUNDARY
+    } else if (prev.is_lowercase() && cur.is_uppercase()) || (!prev.is_numeric() && cur.is_numeric()) {
+        BONUS_CAMEL
+    } else {
+        0
+    }
+}
+
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let pattern: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).map(fold).collect();
+    if pattern.is_empty() {
+        return Some(FuzzyMatch::default());
+    }
+
+    let chars: Vec<char> = candidate.chars().collect();
+    let folded: Vec<char> = chars.iter().map(|&c| fold(c)).collect();
+    let (n, m) = (pattern.len(), chars.len());
+    if n > m {
+        return None;
+    }
+
+    // best[i][j]: best score with pattern[i] matched at chars[j]
+    // parent[i][j]: where pattern[i - 1] was matched on that best path
+    let mut best = vec![vec![None::<i64>; m]; n];
+    let mut parent = vec![vec![0usize; m]; n];
+
+    for i in 0..n {
+        for j in i..m {
+            if folded[j] != pattern[i] {
+                continue;
+            }
+            let base = SCORE_MATCH + position_bonus(&chars, j);
+            if i == 0 {
+                best[i][j] = Some(base);
+                continue;
+            }
+            let mut top: Option<(i64, usize)> = None;
+            for (k, prev) in best[i - 1].iter().enumerate().take(j).skip(i - 1) {
+                let Some(prev) = *prev else { continue };
+                let link = if k + 1 == j {
+                    BONUS_CONSECUTIVE
+                } else {
+                    -(PENALTY_GAP_START + PENALTY_GAP_EXTENSION * (j - k - 2) as i64)
+                };
+                let score = prev + link;
+                if top.is_none_or(|(s, _)| score > s) {
+                    top = Some((score, k));
+                }
+            }
+            if let Some((score, k)) = top {
+                best[i][j] = Some(score + base);
+                parent[i][j] = k;
+            }
+        }
+    }
+
+    let (mut j, score) = best[n - 1]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, s)| s.map(|s| (j, s)))
+        .fold(None, |acc: Option<(usize, i64)>, (j, s)| match acc {
+            Some((_, top)) if top >= s => acc,
+            _ => Some((j, s)),
+        })?;
+
+    let mut positions = vec![0; n];
+    for i in (0..n).rev() {
+        positions[i] = j;
+        if i > 0 {
+            j = parent[i][j];
+        }
+    }
+
+    Some(FuzzyMatch { score, positions })
+}
@@ -0,0 +1,110 @@
+use crate::config::App;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Oldest entries are dropped once the history grows past this many launches
+const MAX_RECORDS: usize = 1000;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct LaunchRecord {
+    // Missing in records written before apps had ids
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    pub key: String,
+    pub cmd: String,
+    // Seconds since the Unix epoch
+    pub timestamp: u64,
+    // None when the process was terminated by a signal, or not waited for
+    // (apps in detach, exec and terminal mode)
+    pub exit_code: Option<i32>,
+}
+
+impl LaunchRecord {
+    // Older records fall back to the name and key the app had at the time
+    fn is_for(&self, app: &App) -> bool {
+        match &self.id {
+            Some(id) => *id == app.id,
+            None => self.name == app.name && self.key == app.key,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct History {
+    #[serde(default)]
+    pub launches: Vec<LaunchRecord>,
+}
+
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+// $XDG_STATE_HOME/term-launcher/history.toml, falling back to ~/.local/state
+pub fn default_path() -> Option<PathBuf> {
+    let state_dir = match env::var("XDG_STATE_HOME") {
+        Ok(dir) if Path::new(&dir).is_absolute() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok()?).join(".local/state"),
+    };
+    Some(state_dir.join("term-launcher/history.toml"))
+}
+
+// Weight of a single launch, decaying with its age
+fn recency_weight(age: u64) -> u64 {
+    match age {
+        a if a < 4 * HOUR => 100,
+        a if a < DAY => 80,
+        a if a < 7 * DAY => 60,
+        a if a < 30 * DAY => 40,
+        a if a < 90 * DAY => 20,
+        _ => 10,
+    }
+}
+
+impl History {
+    // A missing or unreadable history is treated as empty rather than fatal
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let toml_string = toml::to_string(self).map_err(std::io::Error::other)?;
+        fs::write(path, toml_string)
+    }
+
+    pub fn record(&mut self, app: &App, exit_code: Option<i32>, timestamp: u64) {
+        self.launches.push(LaunchRecord {
+            id: Some(app.id.clone()).filter(|id| !id.is_empty()),
+            name: app.name.clone(),
+            key: app.key.clone(),
+            cmd: app.cmd.clone(),
+            timestamp,
+            exit_code,
+        });
+        if self.launches.len() > MAX_RECORDS {
+            let excess = self.launches.len() - MAX_RECORDS;
+            self.launches.drain(..excess);
+        }
+    }
+
+    // Frequency weighted by recency: every launch contributes, recent ones more
+    pub fn frecency(&self, app: &App, now: u64) -> u64 {
+        self.launches
+            .iter()
+            .filter(|r| r.is_for(app))
+            .map(|r| recency_weight(now.saturating_sub(r.timestamp)))
+            .sum()
+    }
+}
@@ -0,0 +1,381 @@
+// Keybindings for the main TUI actions.
+//
+// Every action has a default binding that the `[keys]` config table can
+// replace with one or more combos ("ctrl+a", "alt+x", "shift+tab", "/", "f2"),
+// or clear with an empty list. Plain characters that are not bound to an
+// action are left for app hotkeys.
+//
+// Vim mode adds fixed keys on top (j/k, gg/G, Ctrl+d/u, n/N, dd, `:`) and
+// moves app hotkeys behind a leader key, so plain letters never launch.
+
+use crate::config::{Config, KeyBinding};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Add,
+    Edit,
+    Delete,
+    Theme,
+    Quit,
+    Search,
+    Launch,
+    MoveUp,
+    MoveDown,
+    PageUp,
+    PageDown,
+    First,
+    Last,
+    NextGroup,
+    PrevGroup,
+    CollapseGroup,
+    ExpandGroup,
+    FoldAll,
+}
+
+// Config name and default combos of every action
+const ACTIONS: [(Action, &str, &[&str]); 18] = [
+    (Action::Add, "add", &["ctrl+a"]),
+    (Action::Edit, "edit", &["ctrl+e"]),
+    (Action::Delete, "delete", &["ctrl+d"]),
+    (Action::Theme, "theme", &["ctrl+t"]),
+    (Action::Quit, "quit", &["ctrl+q"]),
+    (Action::Search, "search", &["/"]),
+    (Action::Launch, "launch", &["enter"]),
+    (Action::MoveUp, "move_up", &["up"]),
+    (Action::MoveDown, "move_down", &["down"]),
+    (Action::PageUp, "page_up", &["pageup"]),
+    (Action::PageDown, "page_down", &["pagedown"]),
+    (Action::First, "first", &["home"]),
+    (Action::Last, "last", &["end"]),
+    (Action::NextGroup, "next_group", &["tab"]),
+    (Action::PrevGroup, "prev_group", &["shift+tab"]),
+    (Action::CollapseGroup, "collapse_group", &["left"]),
+    (Action::ExpandGroup, "expand_group", &["right"]),
+    (Action::FoldAll, "fold_all", &["ctrl+g"]),
+];
+
+// Selection moves that have no regular action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Top,
+    Bottom,
+    // 1-based row, as typed on the command line
+    Line(usize),
+    HalfPageDown,
+    HalfPageUp,
+    PageDown,
+    PageUp,
+    NextMatch,
+    PrevMatch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VimStep {
+    Action(Action),
+    Motion(Motion),
+    // Open the `:` command line
+    Command,
+    // Start typing an app hotkey
+    Leader,
+    // First half of `gg` or `dd`
+    Pending,
+}
+
+// Vim mode keys; doubled ones must be pressed twice in a row
+const VIM_KEYS: [(&str, bool, VimStep); 10] = [
+    ("j", false, VimStep::Action(Action::MoveDown)),
+    ("k", false, VimStep::Action(Action::MoveUp)),
+    ("g", true, VimStep::Motion(Motion::Top)),
+    ("G", false, VimStep::Motion(Motion::Bottom)),
+    ("ctrl+d", false, VimStep::Motion(Motion::HalfPageDown)),
+    ("ctrl+u", false, VimStep::Motion(Motion::HalfPageUp)),
+    ("n", false, VimStep::Motion(Motion::NextMatch)),
+    ("N", false, VimStep::Motion(Motion::PrevMatch)),
+    ("d", true, VimStep::Action(Action::Delete)),
+    (":", false, VimStep::Command),
+];
+
+// Actions whose default binding a vim key takes over
+const VIM_REPLACED: [Action; 1] = [Action::Delete];
+
+const DEFAULT_LEADER: &str = "space";
+
+// What a `:` command line asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Action(Action),
+    Motion(Motion),
+    ClearSearch,
+}
+
+pub fn parse_command(input: &str) -> Result<Command, String> {
+    let input = input.trim();
+    if let Ok(line) = input.parse::<usize>() {
+        return Ok(Command::Motion(if line == 0 { Motion::Top } else { Motion::Line(line) }));
+    }
+    let command = match input {
+        "q" | "q!" | "qa" | "quit" => Command::Action(Action::Quit),
+        "add" => Command::Action(Action::Add),
+        "e" | "edit" => Command::Action(Action::Edit),
+        "d" | "delete" => Command::Action(Action::Delete),
+        "theme" => Command::Action(Action::Theme),
+        "fold" => Command::Action(Action::FoldAll),
+        "$" => Command::Motion(Motion::Bottom),
+        "noh" | "nohlsearch" => Command::ClearSearch,
+        _ => return Err(format!("Not a command: {}", input)),
+    };
+    Ok(command)
+}
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        ACTIONS.iter().find(|(action, _, _)| *action == self).map(|(_, name, _)| *name).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyCombo {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyCombo {
+    // Shift is already part of a typed character and of BackTab, and terminals
+    // disagree on the case of Ctrl+letter, so those are folded away
+    fn normalized(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        match code {
+            KeyCode::Char(c) if modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                Self { code: KeyCode::Char(c.to_ascii_lowercase()), modifiers: modifiers - KeyModifiers::SHIFT }
+            }
+            KeyCode::Char(_) | KeyCode::BackTab => Self { code, modifiers: modifiers - KeyModifiers::SHIFT },
+            _ => Self { code, modifiers },
+        }
+    }
+
+    // A plain character, which is what app hotkeys are typed with
+    fn plain_char(&self) -> Option<char> {
+        match self.code {
+            KeyCode::Char(c) if self.modifiers.is_empty() => Some(c),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse_combo(input: &str) -> Result<KeyCombo, String> {
+    let invalid = || format!("'{}' is not a key", input);
+    // A lone "+" is the plus key rather than a separator
+    let (mods, key) = match input.rsplit_once('+') {
+        Some((mods, "")) if !mods.is_empty() => (mods.trim_end_matches('+'), "+"),
+        Some((mods, key)) => (mods, key),
+        None => ("", input),
+    };
+
+    let mut modifiers = KeyModifiers::NONE;
+    for part in mods.split('+').filter(|p| !p.is_empty()) {
+        modifiers |= match part.to_lowercase().as_str() {
+            "ctrl" | "control" => KeyModifiers::CONTROL,
+            "alt" | "meta" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return Err(invalid()),
+        };
+    }
+
+    let mut chars = key.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match key.to_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n @ 1..=12) => KeyCode::F(n),
+                _ => return Err(invalid()),
+            },
+        },
+    };
+    Ok(KeyCombo::normalized(code, modifiers))
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::BackTab => write!(f, "Shift+Tab"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct VimKeys {
+    leader: KeyCombo,
+    keys: Vec<(KeyCombo, bool, VimStep)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(KeyCombo, Action)>,
+    vim: Option<VimKeys>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = ACTIONS
+            .iter()
+            .flat_map(|(action, _, defaults)| defaults.iter().filter_map(|combo| parse_combo(combo).ok()).map(|combo| (combo, *action)))
+            .collect();
+        Self { bindings, vim: None }
+    }
+}
+
+impl Keymap {
+    // Applies the `[keys]` table on top of the defaults. Unknown actions, bad
+    // combos, a combo bound twice and plain characters that start an app hotkey
+    // are all reported; callers fall back to the defaults in that case.
+    pub fn from_config(config: &Config) -> Result<Self, Vec<String>> {
+        let mut keymap = Self::default();
+        let mut errors = Vec::new();
+
+        if let Some(vim) = config.vim.as_ref().filter(|v| v.enabled) {
+            let leader = vim.leader.as_deref().unwrap_or(DEFAULT_LEADER);
+            let leader = parse_combo(leader).unwrap_or_else(|e| {
+                errors.push(format!("vim.leader: {}", e));
+                parse_combo(DEFAULT_LEADER).expect("default leader parses")
+            });
+            let keys: Vec<_> = VIM_KEYS.iter().filter_map(|(combo, double, step)| Some((parse_combo(combo).ok()?, *double, *step))).collect();
+            if keys.iter().any(|(combo, _, _)| *combo == leader) {
+                errors.push(format!("vim.leader: {} is already a vim key", leader));
+            }
+            keymap.bindings.retain(|(_, action)| !VIM_REPLACED.contains(action));
+            keymap.vim = Some(VimKeys { leader, keys });
+        }
+
+        for (name, binding) in config.keys.iter().flatten() {
+            let Some((action, _, _)) = ACTIONS.iter().find(|(_, n, _)| n == name) else {
+                errors.push(format!("keys.{}: unknown action", name));
+                continue;
+            };
+            keymap.bindings.retain(|(_, a)| a != action);
+            let combos: &[String] = match binding {
+                KeyBinding::One(combo) if combo.is_empty() => &[],
+                KeyBinding::One(combo) => std::slice::from_ref(combo),
+                KeyBinding::Many(combos) => combos,
+            };
+            for combo in combos {
+                match parse_combo(combo) {
+                    Ok(combo) => keymap.bindings.push((combo, *action)),
+                    Err(e) => errors.push(format!("keys.{}: {}", name, e)),
+                }
+            }
+        }
+
+        for (idx, (combo, action)) in keymap.bindings.iter().enumerate() {
+            if let Some((_, other)) = keymap.bindings[..idx].iter().find(|(c, _)| c == combo) {
+                if other != action {
+                    errors.push(format!("keys: {} is bound to both {} and {}", combo, other.name(), action.name()));
+                }
+                continue;
+            }
+            if let Some(vim) = &keymap.vim {
+                if *combo == vim.leader || vim.keys.iter().any(|(c, _, _)| c == combo) {
+                    errors.push(format!("keys.{}: {} is reserved in vim mode", action.name(), combo));
+                }
+                continue;
+            }
+            if let Some(c) = combo.plain_char()
+                && let Some(app) = config.apps.iter().find(|a| a.key.starts_with(c))
+            {
+                errors.push(format!("keys.{}: {} clashes with the hotkey '{}' of '{}'", action.name(), combo, app.key, app.name));
+            }
+        }
+
+        if errors.is_empty() { Ok(keymap) } else { Err(errors) }
+    }
+
+    pub fn vim_mode(&self) -> bool {
+        self.vim.is_some()
+    }
+
+    // Vim meaning of a key, or None (vim mode off, or not a vim key) to use the
+    // regular bindings. `pending` remembers the first key of `gg`/`dd`.
+    pub fn vim_step(&self, event: &KeyEvent, pending: &mut Option<char>) -> Option<VimStep> {
+        let vim = self.vim.as_ref()?;
+        let pressed = KeyCombo::normalized(event.code, event.modifiers);
+        let first = pending.take();
+        if pressed == vim.leader {
+            return Some(VimStep::Leader);
+        }
+        let (combo, double, step) = vim.keys.iter().find(|(combo, _, _)| *combo == pressed)?;
+        if *double && first != combo.plain_char() {
+            *pending = combo.plain_char();
+            return Some(VimStep::Pending);
+        }
+        Some(*step)
+    }
+
+    pub fn leader_label(&self) -> Option<String> {
+        self.vim.as_ref().map(|vim| vim.leader.to_string())
+    }
+
+    pub fn action(&self, event: &KeyEvent) -> Option<Action> {
+        let pressed = KeyCombo::normalized(event.code, event.modifiers);
+        self.bindings.iter().find(|(combo, _)| *combo == pressed).map(|(_, action)| *action)
+    }
+
+    // Why an app hotkey could never be typed: its first character triggers an action
+    pub fn hotkey_clash(&self, key: &str) -> Option<String> {
+        if self.vim.is_some() {
+            return None;
+        }
+        let first = key.chars().next()?;
+        let (combo, action) = self.bindings.iter().find(|(combo, _)| combo.plain_char() == Some(first))?;
+        Some(format!("Key '{}' starts with '{}' ({}).", key, combo, action.name()))
+    }
+
+    // First combo bound to the action (or its vim key), for help text
+    pub fn label(&self, action: Action) -> Option<String> {
+        let bound = self.bindings.iter().find(|(_, a)| *a == action).map(|(combo, _)| combo.to_string());
+        bound.or_else(|| {
+            let (combo, double, _) = self.vim.as_ref()?.keys.iter().find(|(_, _, step)| *step == VimStep::Action(action))?;
+            Some(if *double { format!("{0}{0}", combo) } else { combo.to_string() })
+        })
+    }
+
+    // "Key:Label" hints for the bound actions, separated by two spaces
+    pub fn help(&self, entries: &[(Action, &str)]) -> String {
+        entries
+            .iter()
+            .filter_map(|(action, text)| self.label(*action).map(|key| format!("{}:{}", key, text)))
+            .collect::<Vec<_>>()
+            .join("  ")
+    }
+}
@@ -1,11 +1,25 @@
+use crate::argv;
+use crate::config::{App, Config, LaunchMode};
 use std::env;
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
 
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+
+// What came of starting an app
+pub enum Outcome {
+    // Ran in the foreground until it exited
+    Exited(ExitStatus),
+    // Started without waiting for it (detach and terminal modes)
+    Started,
+}
 
-fn allowed_bins() -> Vec<PathBuf> {
+fn default_bins() -> Vec<PathBuf> {
     let mut dirs = vec![
         PathBuf::from("/usr/bin"),
         PathBuf::from("/usr/local/bin"),
@@ -17,6 +31,73 @@ fn allowed_bins() -> Vec<PathBuf> {
     dirs
 }
 
+// XDG base directories with their spec defaults relative to $HOME
+fn xdg_dir(var: &str) -> Option<PathBuf> {
+    if let Ok(value) = env::var(var)
+        && Path::new(&value).is_absolute()
+    {
+        return Some(PathBuf::from(value));
+    }
+    let home = PathBuf::from(env::var("HOME").ok()?);
+    match var {
+        "XDG_CONFIG_HOME" => Some(home.join(".config")),
+        "XDG_DATA_HOME" => Some(home.join(".local/share")),
+        "XDG_STATE_HOME" => Some(home.join(".local/state")),
+        "XDG_CACHE_HOME" => Some(home.join(".cache")),
+        "XDG_BIN_HOME" => Some(home.join(".local/bin")),
+        _ => None,
+    }
+}
+
+// Expands a leading `~` and `$XDG_*` / `${XDG_*}` variables. Any other
+// variable is rejected so the allowlist cannot depend on arbitrary environment.
+pub fn expand_dir(raw: &str) -> Option<PathBuf> {
+    let raw = raw.trim();
+    let mut out = String::new();
+    let mut rest = raw;
+
+    if rest == "~" || rest.starts_with("~/") {
+        out.push_str(&env::var("HOME").ok()?);
+        rest = &rest[1..];
+    }
+
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+        let (name, remainder) = if let Some(braced) = rest.strip_prefix('{') {
+            let end = braced.find('}')?;
+            (&braced[..end], &braced[end + 1..])
+        } else {
+            let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            (&rest[..end], &rest[end..])
+        };
+        if !name.starts_with("XDG_") {
+            return None;
+        }
+        out.push_str(xdg_dir(name)?.to_str()?);
+        rest = remainder;
+    }
+    out.push_str(rest);
+
+    let dir = PathBuf::from(out);
+    dir.is_absolute().then_some(dir)
+}
+
+// Built-in safe locations plus any configured extras. Extras that cannot be
+// expanded or are group/world-writable are dropped.
+pub fn allowed_bins(extra_dirs: &[String]) -> Vec<PathBuf> {
+    let mut dirs = default_bins();
+    for raw in extra_dirs {
+        if let Some(dir) = expand_dir(raw)
+            && !dir_world_writable(&dir)
+            && !dirs.contains(&dir)
+        {
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
+
 #[cfg(unix)]
 fn is_executable(meta: &fs::Metadata) -> bool {
     meta.permissions().mode() & 0o111 != 0
@@ -27,37 +108,83 @@ fn is_executable(_meta: &fs::Metadata) -> bool {
     true
 }
 
+// Why a directory is not safe to run commands from: missing, or writable by others
 #[cfg(unix)]
-fn dir_world_writable(dir: &Path) -> bool {
-    if let Ok(meta) = fs::metadata(dir) {
-        let mode = meta.permissions().mode();
-        mode & 0o022 != 0
+fn dir_problem(dir: &Path) -> Option<String> {
+    let Ok(meta) = fs::metadata(dir) else { return Some("it does not exist".to_string()) };
+    let mode = meta.permissions().mode();
+    if mode & 0o020 != 0 {
+        Some(format!("it is group-writable (mode {:o})", mode & 0o7777))
+    } else if mode & 0o002 != 0 {
+        Some(format!("it is world-writable (mode {:o})", mode & 0o7777))
     } else {
-        true
+        None
     }
 }
 
 #[cfg(not(unix))]
-fn dir_world_writable(_dir: &Path) -> bool { false }
+fn dir_problem(dir: &Path) -> Option<String> {
+    (!dir.is_dir()).then(|| "it does not exist".to_string())
+}
+
+fn dir_world_writable(dir: &Path) -> bool {
+    dir_problem(dir).is_some()
+}
+
+// Why an `allowed_dirs` entry is left out of the allowlist, if it is
+pub fn unusable_dir(raw: &str) -> Option<String> {
+    match expand_dir(raw) {
+        Some(dir) => dir_problem(&dir),
+        None => Some("it is not absolute, or uses a variable other than $XDG_*".to_string()),
+    }
+}
 
-fn is_allowed_path(path: &Path) -> bool {
+// Symlinks followed at most when checking where a command leads
+const MAX_LINKS: usize = 40;
+
+fn is_allowed_path(path: &Path, allowed: &[PathBuf]) -> bool {
     if let Ok(canon) = fs::canonicalize(path) {
-        for base in allowed_bins() {
-            if let Ok(base_canon) = fs::canonicalize(base) {
-                if canon.starts_with(&base_canon) {
-                    return true;
-                }
+        for base in allowed {
+            if let Ok(base_canon) = fs::canonicalize(base)
+                && canon.starts_with(&base_canon)
+            {
+                return true;
             }
         }
     }
+    trusted_link(path, allowed)
+}
+
+// A command found directly in an allowed directory may be a symlink leading
+// elsewhere, like a Nix profile's `bin` pointing into the store. It is followed
+// one link at a time and trusted only if no directory a link lands in is
+// writable by others.
+fn trusted_link(path: &Path, allowed: &[PathBuf]) -> bool {
+    let Some(dir) = path.parent().and_then(|dir| fs::canonicalize(dir).ok()) else { return false };
+    if !allowed.iter().any(|base| fs::canonicalize(base).is_ok_and(|base| base == dir)) {
+        return false;
+    }
+    let mut current = path.to_path_buf();
+    for _ in 0..MAX_LINKS {
+        let Ok(target) = fs::read_link(&current) else { return current != path };
+        let target = match current.parent() {
+            Some(parent) if target.is_relative() => parent.join(target),
+            _ => target,
+        };
+        if target.parent().is_none_or(dir_world_writable) {
+            return false;
+        }
+        current = target;
+    }
     false
 }
 
-pub fn resolve_command(cmd: &str) -> Option<PathBuf> {
+pub fn resolve_command(cmd: &str, extra_dirs: &[String]) -> Option<PathBuf> {
+    let allowed = allowed_bins(extra_dirs);
     let candidate = PathBuf::from(cmd);
     if candidate.is_absolute() {
         let meta = fs::metadata(&candidate).ok()?;
-        if meta.is_file() && is_executable(&meta) && is_allowed_path(&candidate) {
+        if meta.is_file() && is_executable(&meta) && is_allowed_path(&candidate, &allowed) {
             return fs::canonicalize(&candidate).ok();
         }
         return None;
@@ -70,13 +197,147 @@ pub fn resolve_command(cmd: &str) -> Option<PathBuf> {
         if !dir.is_absolute() { continue; }
         if dir_world_writable(&dir) { continue; }
         let path = dir.join(cmd);
-        if let Ok(meta) = fs::metadata(&path) {
-            if meta.is_file() && is_executable(&meta) && is_allowed_path(&path) {
-                if let Ok(canon) = fs::canonicalize(&path) {
-                    return Some(canon);
-                }
-            }
+        if let Ok(meta) = fs::metadata(&path)
+            && meta.is_file() && is_executable(&meta) && is_allowed_path(&path, &allowed)
+            && let Ok(canon) = fs::canonicalize(&path)
+        {
+            return Some(canon);
         }
     }
     None
 }
+
+// Resolves the app's executable and final argument list. `cmd` is only ever
+// the program itself; one with arguments written into it fails (see `split_command`).
+pub fn resolve_app(app: &App, extra_dirs: &[String]) -> Option<(PathBuf, Vec<String>)> {
+    let path = resolve_command(&app.cmd, extra_dirs)?;
+    Some((path, app.args.clone().unwrap_or_default()))
+}
+
+// The words of a `cmd` that has its arguments written into it, like "nvim -p",
+// for `term-launcher migrate` to move into `args`. A program path that contains
+// spaces and resolves as a whole is left alone.
+pub fn split_command(app: &App, extra_dirs: &[String]) -> Option<Vec<String>> {
+    if !app.cmd.trim().contains(char::is_whitespace) || resolve_command(&app.cmd, extra_dirs).is_some() {
+        return None;
+    }
+    argv::split(&app.cmd).ok().filter(|words| words.len() > 1)
+}
+
+// Builds the command with the per-app working directory and environment adjustments
+pub fn build_command(app: &App, path: &Path, args: &[String]) -> io::Result<Command> {
+    let mut command = Command::new(path);
+    command.args(args);
+    if let Some(dir) = working_dir(app)? {
+        command.current_dir(dir);
+    }
+    if app.env_clear == Some(true) {
+        command.env_clear();
+    }
+    for var in app.env_remove.iter().flatten() {
+        command.env_remove(var);
+    }
+    if let Some(vars) = &app.env {
+        command.envs(vars);
+    }
+    Ok(command)
+}
+
+fn working_dir(app: &App) -> io::Result<Option<PathBuf>> {
+    let Some(raw) = &app.cwd else { return Ok(None) };
+    expand_dir(raw)
+        .map(Some)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid working directory '{}'", raw)))
+}
+
+// Whether the app asks for any change to the environment it inherits
+pub fn changes_env(app: &App) -> bool {
+    app.env_clear == Some(true) || app.env_remove.as_ref().is_some_and(|v| !v.is_empty()) || app.env.as_ref().is_some_and(|m| !m.is_empty())
+}
+
+// Starts a resolved app as its mode asks. Foreground runs on the caller's
+// terminal, so leave the TUI first; exec only returns if replacing the process failed.
+pub fn launch(app: &App, config: &Config, path: &Path, args: &[String]) -> io::Result<Outcome> {
+    match app.mode.unwrap_or_default() {
+        LaunchMode::Foreground => build_command(app, path, args)?.status().map(Outcome::Exited),
+        LaunchMode::Detach => spawn_detached(build_command(app, path, args)?),
+        LaunchMode::Terminal => spawn_detached(terminal_command(app, config, path, args)?),
+        LaunchMode::Exec => exec(build_command(app, path, args)?),
+    }
+}
+
+// The `[terminal]` emulator running the app. The emulator keeps the launcher's
+// environment (DISPLAY and the like) and gets the app's working directory; the
+// app's environment changes are applied to the app alone by running it through `env`.
+fn terminal_command(app: &App, config: &Config, path: &Path, args: &[String]) -> io::Result<Command> {
+    let terminal = config.terminal.as_ref().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "mode \"terminal\" needs a [terminal] table naming the emulator")
+    })?;
+    let emulator = resolve_command(&terminal.cmd, config.extra_allowed_dirs()).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("terminal '{}' is not in an allowed location", terminal.cmd))
+    })?;
+    let mut command = Command::new(emulator);
+    command.args(&terminal.args);
+    if let Some(dir) = working_dir(app)? {
+        command.current_dir(dir);
+    }
+    if changes_env(app) {
+        let env = resolve_command("env", config.extra_allowed_dirs()).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "'env' is not in an allowed location; it sets the app's environment inside the terminal")
+        })?;
+        // `env` would read a program path containing '=' as another variable
+        if path.to_string_lossy().contains('=') {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("'{}' cannot be run through env; its path contains '='", path.display())));
+        }
+        command.arg(env);
+        if app.env_clear == Some(true) {
+            command.arg("-i");
+        }
+        for var in app.env_remove.iter().flatten() {
+            command.arg("-u").arg(var);
+        }
+        for (name, value) in app.env.iter().flatten() {
+            command.arg(format!("{}={}", name, value));
+        }
+    }
+    command.arg(path).args(args);
+    Ok(command)
+}
+
+// Starts the command in a new session with no terminal, so it neither draws over
+// the TUI nor gets hung up when the launcher's terminal closes
+fn spawn_detached(mut command: Command) -> io::Result<Outcome> {
+    command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    #[cfg(unix)]
+    // SAFETY: setsid is async-signal-safe and only affects the child
+    unsafe {
+        command.pre_exec(|| if libc::setsid() == -1 { Err(io::Error::last_os_error()) } else { Ok(()) });
+    }
+    let mut child = command.spawn()?;
+    // Reaped in the background so finished apps do not linger as zombies
+    std::thread::spawn(move || child.wait());
+    Ok(Outcome::Started)
+}
+
+#[cfg(unix)]
+fn exec(mut command: Command) -> io::Result<Outcome> {
+    Err(command.exec())
+}
+
+#[cfg(not(unix))]
+fn exec(_command: Command) -> io::Result<Outcome> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "mode \"exec\" needs a Unix system"))
+}
+
+pub fn refusal_message(cmd: &str, extra_dirs: &[String]) -> String {
+    let allowed: Vec<String> = allowed_bins(extra_dirs).iter().map(|d| d.display().to_string()).collect();
+    let mut message = format!(
+        "Refusing to launch command: {}\nNot found in allowed locations: {}\nProvide absolute path, place binary in allowed dirs, or add its directory to [security] allowed_dirs.",
+        cmd,
+        allowed.join(", ")
+    );
+    if cmd.trim().contains(char::is_whitespace) {
+        message.push_str("\nArguments belong in `args`, not `cmd`; `term-launcher migrate` moves them there.");
+    }
+    message
+}
@@ -1,114 +1,45 @@
+mod app;
+mod argv;
+mod cli;
 mod config;
+mod config_doc;
+mod fuzzy;
+mod history;
+mod keys;
 mod launcher;
-
-use config::{Config, App};
+mod query;
+#[macro_use]
+mod render;
+mod view;
+mod watch;
+
+use config::{Config, App, LaunchMode};
+use history::History;
+use render::Backend;
+use std::collections::VecDeque;
 use std::env;
 use std::fs;
-use std::io::{self, Write, stdout};
-use std::path::{PathBuf};
+use std::io::{self, stdout};
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::{Duration, Instant};
 
 use crossterm::{
     cursor,
-    event::{self, Event, KeyCode, KeyModifiers},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers, MouseEventKind},
     execute,
-    terminal::{self, ClearType},
-    style::{self, Color},
+    terminal,
 };
 
-#[derive(Clone, Debug)]
-struct FormField {
-    label: &'static str,
-    value: String,
-    cursor_pos: usize,
-}
-
-#[derive(Clone, Debug)]
-struct FormState {
-    title: &'static str,
-    fields: Vec<FormField>,
-    active_field: usize,
-    error_message: Option<String>,
-    is_edit: bool,
-}
-
-#[derive(Clone, Debug, PartialEq)]
-enum ModalState {
-    None,
-    Form,
-    DeleteConfirm,
-    ThemeSelect,
-}
-
-fn parse_color(s: &str) -> Color {
-    match s.to_lowercase().as_str() {
-        "black" => Color::Black,
-        "dark_red" | "darkred" => Color::DarkRed,
-        "dark_green" | "darkgreen" => Color::DarkGreen,
-        "dark_yellow" | "darkyellow" => Color::DarkYellow,
-        "dark_blue" | "darkblue" => Color::DarkBlue,
-        "dark_magenta" | "darkmagenta" => Color::DarkMagenta,
-        "dark_cyan" | "darkcyan" => Color::DarkCyan,
-        "grey" => Color::Grey,
-        "dark_grey" | "darkgrey" => Color::DarkGrey,
-        "red" => Color::Red,
-        "green" => Color::Green,
-        "yellow" => Color::Yellow,
-        "blue" => Color::Blue,
-        "magenta" => Color::Magenta,
-        "cyan" => Color::Cyan,
-        "white" => Color::White,
-        _ => Color::Reset,
-    }
-}
-
-struct PredefinedTheme {
-    name: &'static str,
-    border_color: &'static str,
-    accent_color: &'static str,
-    text_color: &'static str,
-    dim_color: &'static str,
-}
-
-const PREDEFINED_THEMES: &[PredefinedTheme] = &[
-    PredefinedTheme { name: "Default Blue", border_color: "blue", accent_color: "cyan", text_color: "white", dim_color: "dark_grey" },
-    PredefinedTheme { name: "Cyberpunk Neon", border_color: "cyan", accent_color: "magenta", text_color: "white", dim_color: "grey" },
-    PredefinedTheme { name: "Nordic Frost", border_color: "dark_cyan", accent_color: "cyan", text_color: "grey", dim_color: "dark_grey" },
-    PredefinedTheme { name: "Gruvbox Autumn", border_color: "dark_yellow", accent_color: "yellow", text_color: "white", dim_color: "grey" },
-    PredefinedTheme { name: "Dracula Night", border_color: "dark_magenta", accent_color: "magenta", text_color: "white", dim_color: "dark_grey" },
-    PredefinedTheme { name: "Matrix Terminal", border_color: "dark_green", accent_color: "green", text_color: "green", dim_color: "dark_grey" },
-    PredefinedTheme { name: "Sunset Crimson", border_color: "dark_red", accent_color: "red", text_color: "white", dim_color: "dark_grey" },
-];
-
-struct ActiveTheme {
-    border: Color,
-    accent: Color,
-    text: Color,
-    dim: Color,
-    title: Color,
-}
-
-impl ActiveTheme {
-    fn from_config(config: &Config) -> Self {
-        let border = config.theme.as_ref().and_then(|t| t.border_color.as_ref()).map(|s| parse_color(s)).unwrap_or(Color::Blue);
-        let accent = config.theme.as_ref().and_then(|t| t.accent_color.as_ref()).map(|s| parse_color(s)).unwrap_or(Color::Cyan);
-        let text = config.theme.as_ref().and_then(|t| t.text_color.as_ref()).map(|s| parse_color(s)).unwrap_or(Color::White);
-        let dim = config.theme.as_ref().and_then(|t| t.dim_color.as_ref()).map(|s| parse_color(s)).unwrap_or(Color::DarkGrey);
-        let title = Color::Yellow;
-        Self { border, accent, text, dim, title }
-    }
-}
-
-fn sanitize_for_tui(s: &str) -> String {
-    s.chars().filter(|c| !c.is_control()).collect()
-}
+// How often the event loop wakes up to check the config file for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
 
 struct TerminalGuard;
 
 impl TerminalGuard {
     fn enter() -> std::io::Result<Self> {
         terminal::enable_raw_mode()?;
-        execute!(stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        execute!(stdout(), terminal::EnterAlternateScreen, EnableMouseCapture, cursor::Hide)?;
         Ok(Self)
     }
 }
@@ -116,7 +47,7 @@ impl TerminalGuard {
 impl Drop for TerminalGuard {
     fn drop(&mut self) {
         let _ = terminal::disable_raw_mode();
-        let _ = execute!(stdout(), terminal::LeaveAlternateScreen, cursor::Show);
+        let _ = execute!(stdout(), DisableMouseCapture, terminal::LeaveAlternateScreen, cursor::Show);
     }
 }
 
@@ -128,905 +59,284 @@ fn pause_with_message(msg: &str) -> io::Result<()> {
     Ok(())
 }
 
-fn launch_app(app: &App) -> io::Result<()> {
-    // Leave raw mode and screen for launching
+// Runs the app as its mode asks; returns the event reporting how it went, if any
+fn launch_app(app: &App, config: &Config, history: &mut History, history_path: Option<&Path>, backend: &mut impl Backend) -> io::Result<Option<app::Event>> {
+    let extra_dirs = config.extra_allowed_dirs();
+
+    // Apps started in the background leave the TUI up and report in a toast
+    if matches!(app.mode, Some(LaunchMode::Detach | LaunchMode::Terminal)) {
+        let Some((path, args)) = launcher::resolve_app(app, extra_dirs) else {
+            return Ok(Some(app::Event::LaunchFailed(format!("Refusing to launch {}: not found in an allowed location", app.cmd))));
+        };
+        return Ok(Some(match launcher::launch(app, config, &path, &args) {
+            Ok(_) => app::Event::Launched { app: app.clone(), code: None },
+            Err(e) => app::Event::LaunchFailed(format!("Failed to launch {}: {}", app.name, e)),
+        }));
+    }
+
+    // Leave raw mode and screen for launching; an exec'd app takes over from here
     terminal::disable_raw_mode()?;
-    execute!(stdout(), terminal::LeaveAlternateScreen, cursor::Show)?;
+    execute!(stdout(), DisableMouseCapture, terminal::LeaveAlternateScreen, cursor::Show)?;
 
-    let mut resolved_path = None;
-    let mut final_args = app.args.clone();
+    let mut launched = None;
 
-    // 1. Try standard resolution
-    if let Some(path) = launcher::resolve_command(&app.cmd) {
-        resolved_path = Some(path);
-    } 
-    // 2. Fallback: Try splitting command by whitespace (legacy/malformed config support)
-    else {
-        let parts: Vec<&str> = app.cmd.split_whitespace().collect();
-        if parts.len() > 1 {
-            let base_cmd = parts[0];
-            if let Some(path) = launcher::resolve_command(base_cmd) {
-                resolved_path = Some(path);
-                // Prepend implicit args found in cmd string
-                let implicit_args: Vec<String> = parts[1..].iter().map(|s| s.to_string()).collect();
-                let mut new_args = implicit_args;
-                if let Some(existing) = final_args {
-                    new_args.extend(existing);
-                }
-                final_args = Some(new_args);
+    if let Some((path, args)) = launcher::resolve_app(app, extra_dirs) {
+        // Nothing runs after a successful exec, so it is recorded first
+        if app.mode == Some(LaunchMode::Exec) {
+            history.record(app, None, history::now());
+            if let Some(path) = history_path {
+                let _ = history.save(path);
             }
         }
-    }
-
-    if let Some(path) = resolved_path {
-        // Launch the app
-        let mut command = Command::new(&path);
-        if let Some(args) = &final_args {
-            command.args(args);
-        }
-        let status = command.status();
-
-        match status {
-            Ok(status) => println!("\nProcess exited with status: {}\n", status),
+        match launcher::launch(app, config, &path, &args) {
+            Ok(launcher::Outcome::Exited(status)) => {
+                println!("\nProcess exited with status: {}\n", status);
+                launched = Some(app::Event::Launched { app: app.clone(), code: status.code() });
+            }
+            Ok(launcher::Outcome::Started) => {}
             Err(e) => println!("\nFailed to launch command: {}\n", e),
         }
         pause_with_message("")?;
     } else {
-        println!("Refusing to launch command: {}", app.cmd);
-        println!("Not found in allowed locations: /usr/bin, /usr/local/bin, /bin, ~/.local/bin");
-        println!("Provide absolute path or place binary in allowed dirs.");
+        println!("{}", launcher::refusal_message(&app.cmd, extra_dirs));
         pause_with_message("")?;
     }
 
-    // Restore TUI
+    // Restore TUI; the alternate screen comes back blank, so repaint all of it
     terminal::enable_raw_mode()?;
-    execute!(stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
-    Ok(())
+    execute!(stdout(), terminal::EnterAlternateScreen, EnableMouseCapture, cursor::Hide)?;
+    backend.invalidate();
+    Ok(launched)
 }
 
-fn main() {
-    // Load config
-    let home = env::var("HOME").expect("No HOME env var found");
-    let config_path = PathBuf::from(home).join(".config/term-launcher/config.toml");
-    
-    // Create config dir if not exists
-    if let Some(parent) = config_path.parent() {
-        let _ = fs::create_dir_all(parent);
-    }
-
-    let config = if config_path.exists() {
-        let config_contents = fs::read_to_string(&config_path).expect("Failed to read config");
-        toml::from_str(&config_contents).expect("Failed to parse config")
-    } else {
-        Config { apps: vec![], theme: None }
-    };
-
-    // TUI setup with guard to ensure cleanup on panic/exit
-    let _guard = TerminalGuard::enter().expect("Failed to initialize terminal UI");
-
-    if let Err(e) = run_app(config, config_path) {
-        eprintln!("Application error: {}", e);
-    }
+// Saves unless running on an in-memory config after a failed load; returns the config
+// as written, with the new stamp, so a conflicting external edit can be spotted later
+fn save_config(mut config: Config, config_path: Option<&Path>) -> io::Result<Config> {
+    let Some(path) = config_path else { return Ok(config) };
+    config.save(path)?;
+    Ok(config)
 }
 
-fn run_app(mut config: Config, config_path: PathBuf) -> io::Result<()> {
-    let mut stdout = stdout();
-    let mut selected = 0;
+enum Recovery {
+    Loaded(Box<Config>),
+    InMemory,
+    Quit,
+}
 
-    let mut modal_state = ModalState::None;
-    let mut active_form: Option<FormState> = None;
-    let mut selected_theme_idx = 0;
+// Runs $VISUAL/$EDITOR (default vi) on the file, resolved through the same allowlist as apps
+fn open_in_editor(path: &Path) -> io::Result<()> {
+    let editor = env::var("VISUAL").or_else(|_| env::var("EDITOR")).unwrap_or_else(|_| "vi".to_string());
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    let resolved = launcher::resolve_command(program, &[]).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("editor '{}' is not in an allowed location", program))
+    })?;
 
-    let mut search_query = String::new();
-    let mut search_active = false;
-    let mut search_cursor_pos = 0;
+    terminal::disable_raw_mode()?;
+    execute!(stdout(), DisableMouseCapture, terminal::LeaveAlternateScreen, cursor::Show)?;
+    let status = Command::new(resolved).args(parts).arg(path).status();
+    terminal::enable_raw_mode()?;
+    execute!(stdout(), terminal::EnterAlternateScreen, EnableMouseCapture, cursor::Hide)?;
+    status.map(|_| ())
+}
 
+fn recover_config(config_path: &Path, mut error: config::ParseError) -> io::Result<Recovery> {
+    let mut backend = render::Terminal::default();
+    let mut status: Option<String> = None;
     loop {
-        let active_theme = ActiveTheme::from_config(&config);
-
-        // Filter apps dynamically
-        let filtered_apps: Vec<&App> = config.apps.iter()
-            .filter(|app| {
-                search_query.is_empty() || 
-                app.name.to_lowercase().contains(&search_query.to_lowercase())
-            })
-            .collect();
-
-        // Clamp selected
-        if !filtered_apps.is_empty() && selected >= filtered_apps.len() {
-            selected = filtered_apps.len() - 1;
-        }
-
-        // Clear screen
-        execute!(
-            stdout,
-            terminal::Clear(ClearType::All),
-            cursor::Hide
-        )?;
-
-        let (term_cols, term_rows) = terminal::size()?;
-        
-        // Calculate menu dimensions
-        // Box Width: at least 80, max 80% of screen
-        let box_width = std::cmp::max(90, (term_cols as f32 * 0.8) as u16);
-        let box_height = std::cmp::max(12, (term_rows as f32 * 0.6) as u16);
-        
-        let start_x = (term_cols.saturating_sub(box_width)) / 2;
-        let start_y = (term_rows.saturating_sub(box_height)) / 2;
-
-        let inner_width = box_width.saturating_sub(2);
-        let left_pane_width = (inner_width as f32 * 0.55) as u16;
-        let right_pane_width = inner_width.saturating_sub(left_pane_width).saturating_sub(1);
-        let divider_x = start_x + 1 + left_pane_width;
-
-        // Draw Border
-        execute!(stdout, style::SetForegroundColor(active_theme.border))?;
-        
-        // Top
-        let left_top_dashes = "─".repeat(left_pane_width as usize);
-        let right_top_dashes = "─".repeat(right_pane_width as usize);
-        execute!(stdout, cursor::MoveTo(start_x, start_y))?;
-        write!(stdout, "╭{}┬{}╮", left_top_dashes, right_top_dashes)?;
-
-        // Sides and divider background
-        let left_spaces = " ".repeat(left_pane_width as usize);
-        let right_spaces = " ".repeat(right_pane_width as usize);
-        for i in 1..box_height.saturating_sub(1) {
-            execute!(stdout, cursor::MoveTo(start_x, start_y + i))?;
-            write!(stdout, "│{}│{}│", left_spaces, right_spaces)?;
-        }
-
-        // Bottom
-        let left_bottom_dashes = "─".repeat(left_pane_width as usize);
-        let right_bottom_dashes = "─".repeat(right_pane_width as usize);
-        execute!(stdout, cursor::MoveTo(start_x, start_y + box_height - 1))?;
-        write!(stdout, "╰{}┴{}╯", left_bottom_dashes, right_bottom_dashes)?;
-        
-        execute!(stdout, style::ResetColor)?;
-
-        // Title
-        let title = " Term Launcher ";
-        let title_start_x = start_x + 1 + (left_pane_width.saturating_sub(title.len() as u16)) / 2;
-        execute!(stdout, cursor::MoveTo(title_start_x, start_y), style::SetForegroundColor(active_theme.title), style::SetAttribute(style::Attribute::Bold))?;
-        write!(stdout, "{}", title)?;
-        execute!(stdout, style::ResetColor)?;
-
-        // Draw horizontal divider under search bar
-        execute!(stdout, style::SetForegroundColor(active_theme.border))?;
-        execute!(stdout, cursor::MoveTo(start_x, start_y + 3))?;
-        write!(stdout, "├{}┼", "─".repeat(left_pane_width as usize))?;
-        execute!(stdout, style::ResetColor)?;
-
-        // Help Text (Left bottom border)
-        let left_help = " Ctrl+a:Add  Ctrl+d:Del  Ctrl+e:Edit  /:Search ";
-        let left_help_x = start_x + 1 + (left_pane_width.saturating_sub(left_help.len() as u16)) / 2;
-        execute!(stdout, cursor::MoveTo(left_help_x, start_y + box_height - 1), style::SetForegroundColor(active_theme.dim))?;
-        write!(stdout, "{}", left_help)?;
-        
-        // Help Text (Right bottom border)
-        let right_help = " Ctrl+t:Theme  Ctrl+q:Quit ";
-        let right_help_x = divider_x + 1 + (right_pane_width.saturating_sub(right_help.len() as u16)) / 2;
-        execute!(stdout, cursor::MoveTo(right_help_x, start_y + box_height - 1), style::SetForegroundColor(active_theme.dim))?;
-        write!(stdout, "{}", right_help)?;
-        execute!(stdout, style::ResetColor)?;
-
-        // Draw Search Bar
-        let search_label = " 🔎 Search: ";
-        let search_y = start_y + 2;
-        execute!(stdout, cursor::MoveTo(start_x + 2, search_y))?;
-        if search_active {
-            execute!(stdout, style::SetForegroundColor(active_theme.accent), style::SetAttribute(style::Attribute::Bold))?;
-        } else {
-            execute!(stdout, style::SetForegroundColor(active_theme.title))?;
-        }
-        write!(stdout, "{}", search_label)?;
-        execute!(stdout, style::ResetColor)?;
-
-        // Search Input box
-        execute!(stdout, cursor::MoveTo(start_x + 12, search_y))?;
-        if search_active {
-            execute!(stdout, style::SetForegroundColor(active_theme.accent))?;
-        } else {
-            execute!(stdout, style::SetForegroundColor(active_theme.dim))?;
-        }
-        write!(stdout, "[")?;
-        
-        execute!(stdout, cursor::MoveTo(start_x + 13, search_y), style::SetForegroundColor(active_theme.text))?;
-        let search_inner_width = left_pane_width.saturating_sub(15);
-        let mut display_search = search_query.clone();
-        if display_search.len() > search_inner_width as usize {
-            display_search.truncate(search_inner_width as usize);
-        }
-        write!(stdout, "{}", display_search)?;
-        // Pad spaces
-        let spaces = search_inner_width.saturating_sub(display_search.len() as u16);
-        write!(stdout, "{}", " ".repeat(spaces as usize))?;
-
-        if search_active {
-            execute!(stdout, style::SetForegroundColor(active_theme.accent))?;
-        } else {
-            execute!(stdout, style::SetForegroundColor(active_theme.dim))?;
-        }
-        write!(stdout, "]")?;
-        execute!(stdout, style::ResetColor)?;
-
-        // Content Area (List starts below search divider)
-        let content_start_y = start_y + 4;
-        let max_items = box_height.saturating_sub(6) as usize;
-
-        // Scroll logic (basic)
-        let start_index = if selected >= max_items {
-            selected - max_items + 1
-        } else {
-            0
+        let has_backup = config::last_good_path(config_path).exists();
+        let size = backend.size()?;
+        let mut frame = render::Buffer::new(size.0, size.1);
+        view::RecoveryView { error: &error, status: status.as_deref(), has_backup }.render(size, &mut frame)?;
+        backend.present(frame, None)?;
+
+        // Mouse events would only redraw the same screen
+        let key_event = loop {
+            if let Event::Key(key_event) = event::read()? {
+                break key_event;
+            }
         };
-        let end_index = std::cmp::min(filtered_apps.len(), start_index + max_items);
-
-        let display_apps = &filtered_apps[start_index..end_index];
-
-        if filtered_apps.is_empty() {
-             let msg = "No apps found.";
-             let msg_x = start_x + 1 + (left_pane_width.saturating_sub(msg.len() as u16)) / 2;
-             execute!(stdout, cursor::MoveTo(msg_x, content_start_y), style::SetForegroundColor(active_theme.dim))?;
-             write!(stdout, "{}", msg)?;
-             execute!(stdout, style::ResetColor)?;
-        }
-
-        for (i, app) in display_apps.iter().enumerate() {
-            let actual_idx = start_index + i;
-            let row = content_start_y + i as u16;
-            
-            // Format name and key
-            let key_str = format!("({})", sanitize_for_tui(&app.key));
-            let name_str = sanitize_for_tui(&app.name);
-            
-            let line_start_x = start_x + 1 + (left_pane_width.saturating_sub((name_str.len() + 1 + key_str.len()) as u16)) / 2;
-            
-            if actual_idx == selected {
-                // Selected: highlight with accent background
-                let line = format!("{} {}", name_str, key_str);
-                let marked_line = format!("> {} <", line);
-                let marked_start_x = start_x + 1 + (left_pane_width.saturating_sub(marked_line.len() as u16)) / 2;
-                
-                execute!(stdout, cursor::MoveTo(marked_start_x, row), style::SetForegroundColor(Color::Black), style::SetBackgroundColor(active_theme.accent))?;
-                write!(stdout, "{}", marked_line)?;
-                execute!(stdout, style::ResetColor)?;
-            } else {
-                // Not selected: substring highlight
-                execute!(stdout, cursor::MoveTo(line_start_x, row))?;
-                let mut match_found = false;
-                if !search_query.is_empty() {
-                    if let Some(pos) = name_str.to_lowercase().find(&search_query.to_lowercase()) {
-                        match_found = true;
-                        let prefix = &name_str[..pos];
-                        let matched = &name_str[pos..pos + search_query.len()];
-                        let suffix = &name_str[pos + search_query.len()..];
-
-                        execute!(stdout, style::SetForegroundColor(active_theme.text))?;
-                        write!(stdout, "{}", prefix)?;
-                        
-                        execute!(stdout, style::SetForegroundColor(active_theme.accent), style::SetAttribute(style::Attribute::Bold))?;
-                        write!(stdout, "{}", matched)?;
-                        
-                        execute!(stdout, style::SetForegroundColor(active_theme.text), style::SetAttribute(style::Attribute::Reset))?;
-                        write!(stdout, "{}", suffix)?;
-                    }
+        let attempt = match key_event.code {
+            KeyCode::Char('e') => match open_in_editor(config_path) {
+                Ok(()) => {
+                    backend.invalidate();
+                    Config::load(config_path)
                 }
-                
-                if !match_found {
-                    execute!(stdout, style::SetForegroundColor(active_theme.text))?;
-                    write!(stdout, "{}", name_str)?;
+                Err(e) => {
+                    backend.invalidate();
+                    status = Some(format!("Could not open editor: {}", e));
+                    continue;
                 }
-
-                execute!(stdout, style::SetForegroundColor(active_theme.dim))?;
-                write!(stdout, " {}", key_str)?;
-                execute!(stdout, style::ResetColor)?;
-            }
-        }    
-
-        // Draw Right Pane Details
-        if !filtered_apps.is_empty() {
-            let app = filtered_apps[selected];
-            let right_x = divider_x + 2;
-            let inner_r_width = right_pane_width.saturating_sub(4) as usize;
-            
-            let mut r_row = content_start_y;
-
-            // 1. Draw Title
-            let details_title = " App Details ";
-            let details_title_x = divider_x + 1 + (right_pane_width.saturating_sub(details_title.len() as u16)) / 2;
-            execute!(stdout, cursor::MoveTo(details_title_x, start_y + 1), style::SetForegroundColor(active_theme.accent), style::SetAttribute(style::Attribute::Bold))?;
-            write!(stdout, "{}", details_title)?;
-            execute!(stdout, style::ResetColor)?;
-
-            let mut draw_detail_line = |stdout: &mut io::Stdout, label: &str, value: &str, label_color: Color, val_color: Color| -> io::Result<()> {
-                if r_row >= start_y + box_height - 1 {
-                    return Ok(());
+            },
+            KeyCode::Char('b') if has_backup => match config::restore_last_good(config_path) {
+                Ok(()) => Config::load(config_path),
+                Err(e) => {
+                    status = Some(format!("Could not restore backup: {}", e));
+                    continue;
                 }
-                execute!(stdout, cursor::MoveTo(right_x, r_row))?;
-                
-                let label_part = format!("{}: ", label);
-                let available_val_width = inner_r_width.saturating_sub(label_part.len());
-                let mut val_part = value.to_string();
-                if val_part.len() > available_val_width {
-                    val_part.truncate(available_val_width);
+            },
+            KeyCode::Char('n') => return Ok(Recovery::InMemory),
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(Recovery::Quit),
+            KeyCode::Char('c') if key_event.modifiers == KeyModifiers::CONTROL => return Ok(Recovery::Quit),
+            _ => continue,
+        };
+        match attempt {
+            Ok(config) => return Ok(Recovery::Loaded(Box::new(config))),
+            Err(e) => match config::parse_error(&e) {
+                Some(parse_error) => {
+                    error = parse_error.clone();
+                    status = Some("The file still does not parse.".to_string());
                 }
-                
-                execute!(stdout, style::SetForegroundColor(label_color))?;
-                write!(stdout, "{}", label_part)?;
-                execute!(stdout, style::SetForegroundColor(val_color))?;
-                write!(stdout, "{}", val_part)?;
-                execute!(stdout, style::ResetColor)?;
-                
-                r_row += 1;
-                Ok(())
-            };
-
-            // 2. Name
-            draw_detail_line(&mut stdout, "Name", &app.name, active_theme.title, active_theme.text)?;
-
-            // 3. Hotkey
-            draw_detail_line(&mut stdout, "Hotkey", &app.key, active_theme.title, active_theme.text)?;
-
-            // 4. Command
-            draw_detail_line(&mut stdout, "Command", &app.cmd, active_theme.title, active_theme.text)?;
-
-            // 5. Resolved Path
-            let path_resolved = launcher::resolve_command(&app.cmd);
-            let (path_str, path_color) = if let Some(path) = path_resolved {
-                (path.to_string_lossy().into_owned(), Color::Green)
-            } else {
-                ("Not found / Blocked".to_string(), Color::Red)
-            };
-            draw_detail_line(&mut stdout, "Resolved", &path_str, active_theme.title, path_color)?;
-
-            // 6. Arguments
-            let args_str = match &app.args {
-                Some(args) if !args.is_empty() => args.join(" "),
-                _ => "None".to_string(),
-            };
-            draw_detail_line(&mut stdout, "Args", &args_str, active_theme.title, active_theme.text)?;
-
-            // 7. Description
-            let desc_str = app.description.as_deref().unwrap_or("No description provided");
-            let desc_color = if app.description.is_some() { active_theme.text } else { active_theme.dim };
-            draw_detail_line(&mut stdout, "Desc", desc_str, active_theme.title, desc_color)?;
+                None => status = Some(format!("Could not read config: {}", e)),
+            },
         }
+    }
+}
 
-        // Draw Form Modal Overlay
-        if modal_state == ModalState::Form {
-            if let Some(ref form) = active_form {
-                let modal_width = 60;
-                let modal_height = 14;
-                let modal_x = (term_cols.saturating_sub(modal_width)) / 2;
-                let modal_y = (term_rows.saturating_sub(modal_height)) / 2;
-
-                execute!(stdout, style::SetForegroundColor(active_theme.accent))?;
-                
-                // Top
-                let title_bar = format!(" {} ", form.title);
-                let dash_len = (modal_width as usize - 2 - title_bar.len()) / 2;
-                let left_dashes = "═".repeat(dash_len);
-                let right_dashes = "═".repeat(modal_width as usize - 2 - title_bar.len() - dash_len);
-                execute!(stdout, cursor::MoveTo(modal_x, modal_y))?;
-                write!(stdout, "╔{}{}{}╗", left_dashes, title_bar, right_dashes)?;
-
-                // Sides
-                for r in 1..modal_height - 1 {
-                    execute!(stdout, cursor::MoveTo(modal_x, modal_y + r))?;
-                    write!(stdout, "║{}║", " ".repeat((modal_width - 2) as usize))?;
-                }
-
-                // Bottom
-                execute!(stdout, cursor::MoveTo(modal_x, modal_y + modal_height - 1))?;
-                write!(stdout, "╚{}╝", "═".repeat((modal_width - 2) as usize))?;
-                execute!(stdout, style::ResetColor)?;
-
-                // Draw fields
-                for (idx, field) in form.fields.iter().enumerate() {
-                    let field_y = modal_y + 3 + (2 * idx) as u16;
-                    
-                    // Label
-                    execute!(stdout, cursor::MoveTo(modal_x + 3, field_y))?;
-                    if idx == form.active_field {
-                        execute!(stdout, style::SetForegroundColor(active_theme.accent), style::SetAttribute(style::Attribute::Bold))?;
-                    } else {
-                        execute!(stdout, style::SetForegroundColor(active_theme.title))?;
-                    }
-                    write!(stdout, "{:11}", field.label)?;
-                    execute!(stdout, style::ResetColor)?;
-
-                    // Input bracket
-                    execute!(stdout, cursor::MoveTo(modal_x + 15, field_y))?;
-                    if idx == form.active_field {
-                        execute!(stdout, style::SetForegroundColor(active_theme.accent))?;
-                    } else {
-                        execute!(stdout, style::SetForegroundColor(active_theme.dim))?;
-                    }
-                    write!(stdout, "[")?;
-                    
-                    // Value
-                    execute!(stdout, cursor::MoveTo(modal_x + 16, field_y), style::SetForegroundColor(active_theme.text))?;
-                    let val_limit = 39;
-                    let mut display_val = field.value.clone();
-                    if display_val.len() > val_limit {
-                        display_val.truncate(val_limit);
-                    }
-                    write!(stdout, "{}", display_val)?;
-
-                    // Fill remaining input box space
-                    let spaces = val_limit.saturating_sub(display_val.len());
-                    write!(stdout, "{}", " ".repeat(spaces))?;
-
-                    // Close bracket
-                    if idx == form.active_field {
-                        execute!(stdout, style::SetForegroundColor(active_theme.accent))?;
-                    } else {
-                        execute!(stdout, style::SetForegroundColor(active_theme.dim))?;
-                    }
-                    write!(stdout, "]")?;
-                    execute!(stdout, style::ResetColor)?;
-                }
-
-                // Draw buttons/help in modal
-                let form_help = " [Enter] Save   [Esc] Cancel   [Tab] Next ";
-                let form_help_x = modal_x + (modal_width.saturating_sub(form_help.len() as u16)) / 2;
-                execute!(stdout, cursor::MoveTo(form_help_x, modal_y + 11), style::SetForegroundColor(active_theme.dim))?;
-                write!(stdout, "{}", form_help)?;
-                execute!(stdout, style::ResetColor)?;
-
-                // Draw error message if any
-                if let Some(ref err) = form.error_message {
-                    let err_display = format!("Error: {}", err);
-                    let err_x = modal_x + (modal_width.saturating_sub(err_display.len() as u16)) / 2;
-                    execute!(stdout, cursor::MoveTo(err_x, modal_y + 12), style::SetForegroundColor(Color::Red), style::SetAttribute(style::Attribute::Bold))?;
-                    write!(stdout, "{}", err_display)?;
-                    execute!(stdout, style::ResetColor)?;
-                }
-            }
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (options, command_args) = match cli::parse_global_options(&args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
         }
+    };
 
-        // Draw Delete Confirmation Modal Overlay
-        if modal_state == ModalState::DeleteConfirm {
-            if !filtered_apps.is_empty() {
-                let app = filtered_apps[selected];
-                let modal_width = 50;
-                let modal_height = 8;
-                let modal_x = (term_cols.saturating_sub(modal_width)) / 2;
-                let modal_y = (term_rows.saturating_sub(modal_height)) / 2;
-
-                execute!(stdout, style::SetForegroundColor(Color::Red))?;
-                
-                // Top
-                let title = " Confirm Delete ";
-                let dash_len = (modal_width as usize - 2 - title.len()) / 2;
-                let left_dashes = "═".repeat(dash_len);
-                let right_dashes = "═".repeat(modal_width as usize - 2 - title.len() - dash_len);
-                execute!(stdout, cursor::MoveTo(modal_x, modal_y))?;
-                write!(stdout, "╔{}{}{}╗", left_dashes, title, right_dashes)?;
-
-                // Sides
-                for r in 1..modal_height - 1 {
-                    execute!(stdout, cursor::MoveTo(modal_x, modal_y + r))?;
-                    write!(stdout, "║{}║", " ".repeat((modal_width - 2) as usize))?;
-                }
-
-                // Bottom
-                execute!(stdout, cursor::MoveTo(modal_x, modal_y + modal_height - 1))?;
-                write!(stdout, "╚{}╝", "═".repeat((modal_width - 2) as usize))?;
-                execute!(stdout, style::ResetColor)?;
-
-                // Message
-                let msg1 = "Are you sure you want to delete";
-                let msg2 = format!("'{}'?", app.name);
-                let msg1_x = modal_x + (modal_width.saturating_sub(msg1.len() as u16)) / 2;
-                let msg2_x = modal_x + (modal_width.saturating_sub(msg2.len() as u16)) / 2;
-                
-                execute!(stdout, cursor::MoveTo(msg1_x, modal_y + 2), style::SetForegroundColor(Color::White))?;
-                write!(stdout, "{}", msg1)?;
-                execute!(stdout, cursor::MoveTo(msg2_x, modal_y + 3), style::SetForegroundColor(Color::Yellow))?;
-                write!(stdout, "{}", msg2)?;
-
-                // Buttons
-                let btn_help = " [y] Yes      [n/Esc] No ";
-                let btn_x = modal_x + (modal_width.saturating_sub(btn_help.len() as u16)) / 2;
-                execute!(stdout, cursor::MoveTo(btn_x, modal_y + 5), style::SetForegroundColor(Color::White))?;
-                write!(stdout, "{}", btn_help)?;
-                execute!(stdout, style::ResetColor)?;
-            }
+    // Load config
+    let config_path = match config::resolve_config_path(options.config.as_deref(), options.profile.as_deref()) {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
         }
+    };
 
-        // Draw Theme Select Modal Overlay
-        if modal_state == ModalState::ThemeSelect {
-            let modal_width = 44;
-            let modal_height = PREDEFINED_THEMES.len() as u16 + 6;
-            let modal_x = (term_cols.saturating_sub(modal_width)) / 2;
-            let modal_y = (term_rows.saturating_sub(modal_height)) / 2;
-
-            execute!(stdout, style::SetForegroundColor(active_theme.accent))?;
-            
-            // Top
-            let title = " Select Theme ";
-            let dash_len = (modal_width as usize - 2 - title.len()) / 2;
-            let left_dashes = "═".repeat(dash_len);
-            let right_dashes = "═".repeat(modal_width as usize - 2 - title.len() - dash_len);
-            execute!(stdout, cursor::MoveTo(modal_x, modal_y))?;
-            write!(stdout, "╔{}{}{}╗", left_dashes, title, right_dashes)?;
-
-            // Sides
-            for r in 1..modal_height - 1 {
-                execute!(stdout, cursor::MoveTo(modal_x, modal_y + r))?;
-                write!(stdout, "║{}║", " ".repeat((modal_width - 2) as usize))?;
-            }
-
-            // Bottom
-            execute!(stdout, cursor::MoveTo(modal_x, modal_y + modal_height - 1))?;
-            write!(stdout, "╚{}╝", "═".repeat((modal_width - 2) as usize))?;
-            execute!(stdout, style::ResetColor)?;
+    // Create config dir if not exists
+    if let Some(parent) = config_path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
 
-            // Draw themes list
-            for (idx, theme) in PREDEFINED_THEMES.iter().enumerate() {
-                let theme_y = modal_y + 2 + idx as u16;
-                
-                // Format the theme name to fit and center in modal
-                let display_name = format!("  {}  ", theme.name);
-                let text_x = modal_x + (modal_width.saturating_sub(display_name.len() as u16)) / 2;
-                execute!(stdout, cursor::MoveTo(text_x, theme_y))?;
+    if !command_args.is_empty() {
+        std::process::exit(cli::run(command_args, &config_path));
+    }
 
-                if idx == selected_theme_idx {
-                    execute!(stdout, style::SetForegroundColor(Color::Black), style::SetBackgroundColor(active_theme.accent))?;
-                    write!(stdout, " {} ", theme.name)?;
-                    execute!(stdout, style::ResetColor)?;
-                } else {
-                    execute!(stdout, style::SetForegroundColor(active_theme.text))?;
-                    write!(stdout, "  {}  ", theme.name)?;
-                    execute!(stdout, style::ResetColor)?;
-                }
+    // Parse errors are recoverable inside the TUI; anything else is fatal
+    let loaded = match Config::load(&config_path) {
+        Ok(config) => Ok(config),
+        Err(e) => match config::parse_error(&e) {
+            Some(parse_error) => Err(parse_error.clone()),
+            None => {
+                eprintln!("Failed to read {}: {}", config_path.display(), e);
+                std::process::exit(1);
             }
+        },
+    };
 
-            // Draw help/instructions at the bottom
-            let theme_help = " [Up/Down] Navigate  [Enter] Select  [Esc] Cancel ";
-            let theme_help_x = modal_x + (modal_width.saturating_sub(theme_help.len() as u16)) / 2;
-            execute!(stdout, cursor::MoveTo(theme_help_x, modal_y + modal_height - 3), style::SetForegroundColor(active_theme.dim))?;
-            write!(stdout, "{}", theme_help)?;
-            execute!(stdout, style::ResetColor)?;
-        }
+    let history_path = history::default_path();
+    let history = history_path.as_ref().map(History::load).unwrap_or_default();
 
-        // Show/Hide Caret Cursor dynamically
-        let mut show_cursor = false;
-        let mut cursor_x = 0;
-        let mut cursor_y = 0;
+    // TUI setup with guard to ensure cleanup on panic/exit
+    let _guard = TerminalGuard::enter().expect("Failed to initialize terminal UI");
 
-        if search_active {
-            show_cursor = true;
-            cursor_x = start_x + 13 + search_cursor_pos as u16;
-            cursor_y = start_y + 2;
-        } else if modal_state == ModalState::Form {
-            if let Some(ref form) = active_form {
-                show_cursor = true;
-                let modal_width = 60;
-                let modal_x = (term_cols.saturating_sub(modal_width)) / 2;
-                let modal_y = (term_rows.saturating_sub(14)) / 2;
-                
-                cursor_y = modal_y + 3 + (2 * form.active_field) as u16;
-                let active_field_state = &form.fields[form.active_field];
-                cursor_x = modal_x + 16 + active_field_state.cursor_pos as u16;
+    let (config, save_path) = match loaded {
+        Ok(config) => (config, Some(config_path)),
+        Err(parse_error) => match recover_config(&config_path, parse_error) {
+            Ok(Recovery::Loaded(config)) => (*config, Some(config_path)),
+            Ok(Recovery::InMemory) => (Config::default(), None),
+            Ok(Recovery::Quit) => return,
+            Err(e) => {
+                eprintln!("Application error: {}", e);
+                return;
             }
-        }
-
-        if show_cursor {
-            execute!(stdout, cursor::MoveTo(cursor_x, cursor_y), cursor::Show)?;
-        } else {
-            execute!(stdout, cursor::Hide)?;
-        }
-
-        stdout.flush()?;
+        },
+    };
 
-        // Handle key events
-        if let Event::Key(key_event) = event::read()? {
-            match modal_state {
-                ModalState::DeleteConfirm => {
-                    match key_event.code {
-                        KeyCode::Char('y') | KeyCode::Char('Y') => {
-                            if !filtered_apps.is_empty() {
-                                let app_to_delete = filtered_apps[selected];
-                                if let Some(idx) = config.apps.iter().position(|a| a.name == app_to_delete.name && a.key == app_to_delete.key) {
-                                    config.apps.remove(idx);
-                                    let _ = config.save(&config_path);
-                                }
-                            }
-                            modal_state = ModalState::None;
-                        }
-                        KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
-                            modal_state = ModalState::None;
-                        }
-                        _ => {}
-                    }
-                }
-                ModalState::ThemeSelect => {
-                    match key_event.code {
-                        KeyCode::Esc => {
-                            modal_state = ModalState::None;
-                        }
-                        KeyCode::Up => {
-                            if selected_theme_idx > 0 {
-                                selected_theme_idx -= 1;
-                            } else {
-                                selected_theme_idx = PREDEFINED_THEMES.len() - 1;
-                            }
-                        }
-                        KeyCode::Down => {
-                            if selected_theme_idx + 1 < PREDEFINED_THEMES.len() {
-                                selected_theme_idx += 1;
-                            } else {
-                                selected_theme_idx = 0;
-                            }
-                        }
-                        KeyCode::Enter => {
-                            let selected_theme = &PREDEFINED_THEMES[selected_theme_idx];
-                            config.theme = Some(config::Theme {
-                                accent_color: Some(selected_theme.accent_color.to_string()),
-                                border_color: Some(selected_theme.border_color.to_string()),
-                                text_color: Some(selected_theme.text_color.to_string()),
-                                dim_color: Some(selected_theme.dim_color.to_string()),
-                            });
-                            let _ = config.save(&config_path);
-                            modal_state = ModalState::None;
-                        }
-                        _ => {}
-                    }
-                }
-                ModalState::Form => {
-                    if let Some(ref mut form) = active_form {
-                        match key_event.code {
-                            KeyCode::Esc => {
-                                modal_state = ModalState::None;
-                                active_form = None;
-                            }
-                            KeyCode::Tab | KeyCode::Down => {
-                                form.active_field = (form.active_field + 1) % form.fields.len();
-                            }
-                            KeyCode::BackTab | KeyCode::Up => {
-                                form.active_field = (form.active_field + form.fields.len() - 1) % form.fields.len();
-                            }
-                            KeyCode::Left => {
-                                let field = &mut form.fields[form.active_field];
-                                if field.cursor_pos > 0 {
-                                    field.cursor_pos -= 1;
-                                }
-                            }
-                            KeyCode::Right => {
-                                let field = &mut form.fields[form.active_field];
-                                if field.cursor_pos < field.value.len() {
-                                    field.cursor_pos += 1;
-                                }
-                            }
-                            KeyCode::Backspace => {
-                                let field = &mut form.fields[form.active_field];
-                                if field.cursor_pos > 0 {
-                                    field.value.remove(field.cursor_pos - 1);
-                                    field.cursor_pos -= 1;
-                                }
-                            }
-                            KeyCode::Delete => {
-                                let field = &mut form.fields[form.active_field];
-                                if field.cursor_pos < field.value.len() {
-                                    field.value.remove(field.cursor_pos);
-                                }
-                            }
-                            KeyCode::Char(c) => {
-                                let field = &mut form.fields[form.active_field];
-                                if field.value.len() < 39 {
-                                    field.value.insert(field.cursor_pos, c);
-                                    field.cursor_pos += 1;
-                                }
-                            }
-                            KeyCode::Enter => {
-                                let name = form.fields[0].value.trim().to_string();
-                                let key = form.fields[1].value.trim().to_string();
-                                let cmd_input = form.fields[2].value.trim().to_string();
-                                let desc_input = form.fields[3].value.trim().to_string();
+    // Best effort: the file parsed, so it becomes the copy recovery falls back to
+    if let Some(path) = &save_path {
+        let _ = config::keep_last_good(path);
+    }
 
-                                if name.is_empty() || key.is_empty() {
-                                    form.error_message = Some("Name and Key cannot be empty.".to_string());
-                                } else if cmd_input.is_empty() {
-                                    form.error_message = Some("Command cannot be empty.".to_string());
-                                } else {
-                                    let mut key_conflict = false;
-                                    if form.is_edit {
-                                        if !filtered_apps.is_empty() {
-                                            let current_app = filtered_apps[selected];
-                                            key_conflict = config.apps.iter().any(|app| {
-                                                app.key == key && (app.name != current_app.name || app.key != current_app.key)
-                                            });
-                                        }
-                                    } else {
-                                        key_conflict = config.apps.iter().any(|app| app.key == key);
-                                    }
+    if let Err(e) = run_app(config, save_path, history, history_path) {
+        eprintln!("Application error: {}", e);
+    }
+}
 
-                                    if key_conflict {
-                                        form.error_message = Some(format!("Key '{}' is already in use.", key));
-                                    } else {
-                                        let parts: Vec<&str> = cmd_input.split_whitespace().collect();
-                                        let cmd = parts[0].to_string();
-                                        let args = if parts.len() > 1 {
-                                            Some(parts[1..].iter().map(|s| s.to_string()).collect())
-                                        } else {
-                                            None
-                                        };
+// Draws the frame off-screen; the terminal backend writes only what changed since the last one
+fn draw(backend: &mut impl Backend, state: &app::State) -> io::Result<()> {
+    // An invalid [keys] table falls back to the defaults (reported in a toast)
+    let keymap = keys::Keymap::from_config(&state.config).unwrap_or_default();
+    let (ranked, rows) = state.list();
+    let (term_cols, term_rows) = state.size;
+    let mut frame = render::Buffer::new(term_cols, term_rows);
+    let caret = state.view(&keymap, &ranked, &rows).render(&state.layout(), &mut frame)?;
+    backend.present(frame, caret)
+}
 
-                                        let description = if desc_input.is_empty() {
-                                            None
-                                        } else {
-                                            Some(desc_input)
-                                        };
+// Reads the config unless the file still matches `stamp`; our own saves,
+// touches and a briefly missing file are not changes
+fn reload_if_changed(path: &Path, stamp: Option<config::FileStamp>) -> Option<io::Result<Config>> {
+    match config::FileStamp::read(path) {
+        Ok(Some(current)) if Some(current) != stamp => Some(Config::load(path)),
+        _ => None,
+    }
+}
 
-                                        if form.is_edit {
-                                            if !filtered_apps.is_empty() {
-                                                let current_app = filtered_apps[selected];
-                                                if let Some(idx) = config.apps.iter().position(|a| a.name == current_app.name && a.key == current_app.key) {
-                                                    config.apps[idx] = App {
-                                                        name,
-                                                        key,
-                                                        cmd,
-                                                        args,
-                                                        description,
-                                                    };
-                                                }
-                                            }
-                                        } else {
-                                            config.apps.push(App {
-                                                name,
-                                                key,
-                                                cmd,
-                                                args,
-                                                description,
-                                            });
-                                        }
+// Runs the state machine on terminal input, carrying out the effects it asks for
+fn run_app(config: Config, config_path: Option<PathBuf>, history: History, history_path: Option<PathBuf>) -> io::Result<()> {
+    let mut backend = render::Terminal::default();
+    let mut watcher = config_path.as_deref().map(watch::ConfigWatcher::new);
+    let mut state = app::State::new(config, config_path, history, backend.size()?, Instant::now(), history::now(), config::random_seed());
+    let mut events = VecDeque::new();
 
-                                        config.apps.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
-                                        if let Err(e) = config.save(&config_path) {
-                                            form.error_message = Some(format!("Failed to save: {}", e));
-                                        } else {
-                                            modal_state = ModalState::None;
-                                            active_form = None;
-                                        }
-                                    }
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-                ModalState::None => {
-                    if search_active {
-                        match key_event.code {
-                            KeyCode::Esc => {
-                                search_active = false;
-                                search_query.clear();
-                                search_cursor_pos = 0;
-                            }
-                            KeyCode::Enter | KeyCode::Down | KeyCode::Tab => {
-                                search_active = false;
-                            }
-                            KeyCode::Left => {
-                                if search_cursor_pos > 0 {
-                                    search_cursor_pos -= 1;
-                                }
-                            }
-                            KeyCode::Right => {
-                                if search_cursor_pos < search_query.len() {
-                                    search_cursor_pos += 1;
-                                }
-                            }
-                            KeyCode::Backspace => {
-                                if search_cursor_pos > 0 {
-                                    search_query.remove(search_cursor_pos - 1);
-                                    search_cursor_pos -= 1;
-                                }
-                            }
-                            KeyCode::Delete => {
-                                if search_cursor_pos < search_query.len() {
-                                    search_query.remove(search_cursor_pos);
-                                }
-                            }
-                            KeyCode::Char(c) => {
-                                search_query.insert(search_cursor_pos, c);
-                                search_cursor_pos += 1;
-                            }
-                            _ => {}
-                        }
-                    } else {
-                        match (key_event.code, key_event.modifiers) {
-                            (KeyCode::Char('q'), KeyModifiers::CONTROL) => return Ok(()),
-                            (KeyCode::Char('/'), KeyModifiers::NONE) => {
-                                search_active = true;
-                                search_cursor_pos = search_query.len();
-                            }
-                            (KeyCode::Char('d'), KeyModifiers::CONTROL) => {
-                                if !filtered_apps.is_empty() {
-                                    modal_state = ModalState::DeleteConfirm;
-                                }
-                            }
-                            (KeyCode::Char('a'), KeyModifiers::CONTROL) => {
-                                modal_state = ModalState::Form;
-                                active_form = Some(FormState {
-                                    title: "Add New Application",
-                                    fields: vec![
-                                        FormField { label: "Name", value: String::new(), cursor_pos: 0 },
-                                        FormField { label: "Hotkey", value: String::new(), cursor_pos: 0 },
-                                        FormField { label: "Command", value: String::new(), cursor_pos: 0 },
-                                        FormField { label: "Description", value: String::new(), cursor_pos: 0 },
-                                    ],
-                                    active_field: 0,
-                                    error_message: None,
-                                    is_edit: false,
-                                });
-                            }
-                            (KeyCode::Char('e'), KeyModifiers::CONTROL) => {
-                                if !filtered_apps.is_empty() {
-                                    let app = filtered_apps[selected];
-                                    let mut full_cmd_str = app.cmd.clone();
-                                    if let Some(args) = &app.args {
-                                        full_cmd_str.push_str(" ");
-                                        full_cmd_str.push_str(&args.join(" "));
-                                    }
-                                    let current_desc = app.description.clone().unwrap_or_default();
-                                    
-                                    modal_state = ModalState::Form;
-                                    active_form = Some(FormState {
-                                        title: "Edit Application",
-                                        fields: vec![
-                                            FormField { label: "Name", value: app.name.clone(), cursor_pos: app.name.len() },
-                                            FormField { label: "Hotkey", value: app.key.clone(), cursor_pos: app.key.len() },
-                                            FormField { label: "Command", value: full_cmd_str.clone(), cursor_pos: full_cmd_str.len() },
-                                            FormField { label: "Description", value: current_desc.clone(), cursor_pos: current_desc.len() },
-                                        ],
-                                        active_field: 0,
-                                        error_message: None,
-                                        is_edit: true,
-                                    });
-                                }
-                            }
-                            (KeyCode::Char('t'), KeyModifiers::CONTROL) => {
-                                modal_state = ModalState::ThemeSelect;
-                                // Attempt to match selected_theme_idx to currently set theme
-                                if let Some(ref current_theme) = config.theme {
-                                    if let Some(ref current_accent) = current_theme.accent_color {
-                                        if let Some(pos) = PREDEFINED_THEMES.iter().position(|t| t.accent_color == current_accent) {
-                                            selected_theme_idx = pos;
-                                        }
-                                    }
-                                }
-                            }
-                            (KeyCode::Up, _) => {
-                                if selected > 0 {
-                                    selected -= 1;
-                                }
-                            }
-                            (KeyCode::Down, _) => {
-                                if !filtered_apps.is_empty() {
-                                    if selected + 1 < filtered_apps.len() {
-                                        selected += 1;
-                                    }
-                                }
-                            }
-                            (KeyCode::Enter, _) => {
-                                if !filtered_apps.is_empty() {
-                                    let app = filtered_apps[selected];
-                                    launch_app(app)?;
-                                }
-                            }
-                            (KeyCode::Char(c), KeyModifiers::NONE) | (KeyCode::Char(c), KeyModifiers::SHIFT) => {
-                                if let Some(app) = config.apps.iter().find(|a| a.key == c.to_string()) {
-                                    launch_app(app)?;
-                                }
-                            }
-                            _ => {}
+    loop {
+        let size = backend.size()?;
+        if size != state.size {
+            events.push_back(app::Event::Resize(size.0, size.1));
+        }
+        while let Some(event) = events.pop_front() {
+            let effects;
+            (state, effects) = app::update(state, event, Instant::now());
+            for effect in effects {
+                let outcome = match effect {
+                    app::Effect::Quit => return Ok(()),
+                    app::Effect::Launch(app) => launch_app(&app, &state.config, &mut state.history, history_path.as_deref(), &mut backend)?,
+                    app::Effect::Save(config) => Some(app::Event::Saved(save_config(config, state.config_path.as_deref()))),
+                    app::Effect::Overwrite(mut config) => state.config_path.as_deref().map(|path| app::Event::Saved(config::FileStamp::read(path).and_then(|stamp| {
+                        config.stamp = stamp;
+                        save_config(config, Some(path))
+                    }))),
+                    app::Effect::Load => state.config_path.as_deref().map(|path| app::Event::Loaded(Config::load(path))),
+                    app::Effect::ReloadIfChanged(stamp) => state.config_path.as_deref().and_then(|path| reload_if_changed(path, stamp)).map(app::Event::Loaded),
+                    app::Effect::SaveHistory => {
+                        if let Some(path) = history_path.as_deref() {
+                            let _ = state.history.save(path);
                         }
+                        None
                     }
+                };
+                events.extend(outcome);
+            }
+        }
+        draw(&mut backend, &state)?;
+
+        // Wait for input, waking up to pick up config changes and expire the toast
+        if event::poll(WATCH_INTERVAL)? {
+            match event::read()? {
+                Event::Key(key_event) => events.push_back(app::Event::Key(key_event)),
+                // Pointer motion and releases change nothing
+                Event::Mouse(mouse) if !matches!(mouse.kind, MouseEventKind::Moved | MouseEventKind::Drag(_) | MouseEventKind::Up(_)) => {
+                    events.push_back(app::Event::Mouse(mouse));
                 }
+                _ => {}
+            }
+        } else {
+            if let Some(ref mut watcher) = watcher
+                && watcher.changed()
+            {
+                events.push_back(app::Event::ConfigChanged);
             }
+            events.push_back(app::Event::Tick);
         }
     }
 }
@@ -0,0 +1,107 @@
+// Search bar syntax.
+//
+// `#tag` keeps only apps carrying that tag and `-#tag` drops apps carrying it
+// (tags compare case-insensitively). Everything else is free text for the
+// fuzzy matcher, which tries the name, command, tags, description and
+// arguments and keeps the best field after weighting, so a name match
+// outranks an equally good match elsewhere.
+
+use crate::config::App;
+use crate::fuzzy;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Name,
+    Command,
+    Tags,
+    Description,
+    Args,
+}
+
+// Searched fields with their weight in percent of a name match
+const FIELD_WEIGHTS: [(Field, i64); 5] = [
+    (Field::Name, 100),
+    (Field::Command, 80),
+    (Field::Tags, 70),
+    (Field::Description, 60),
+    (Field::Args, 50),
+];
+
+#[derive(Debug, Clone)]
+pub struct AppMatch {
+    pub field: Field,
+    pub score: i64,
+    // Char indices into `field_text` of the matched field
+    pub positions: Vec<usize>,
+}
+
+// The text searched for a field, which is also how the details pane shows it.
+// Control characters are dropped so positions line up with what is drawn.
+pub fn field_text(app: &App, field: Field) -> Option<String> {
+    let text = match field {
+        Field::Name => app.name.clone(),
+        Field::Command => app.cmd.clone(),
+        Field::Tags if !app.tags.is_empty() => app.tags.iter().map(|t| format!("#{}", t)).collect::<Vec<_>>().join(" "),
+        Field::Description => app.description.clone()?,
+        Field::Args => app.args.as_ref().filter(|args| !args.is_empty())?.join(" "),
+        Field::Tags => return None,
+    };
+    Some(text.chars().filter(|c| !c.is_control()).collect())
+}
+
+#[derive(Debug, Default)]
+pub struct Query {
+    pub text: String,
+    pub include_tags: Vec<String>,
+    pub exclude_tags: Vec<String>,
+}
+
+pub fn parse(input: &str) -> Query {
+    let mut query = Query::default();
+    let mut words = Vec::new();
+    for word in input.split_whitespace() {
+        if let Some(tag) = word.strip_prefix("-#") {
+            if !tag.is_empty() {
+                query.exclude_tags.push(tag.to_lowercase());
+            }
+        } else if let Some(tag) = word.strip_prefix('#') {
+            // A lone `#` is a tag still being typed
+            if !tag.is_empty() {
+                query.include_tags.push(tag.to_lowercase());
+            }
+        } else {
+            words.push(word);
+        }
+    }
+    query.text = words.join(" ");
+    query
+}
+
+impl Query {
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.include_tags.is_empty() && self.exclude_tags.is_empty()
+    }
+
+    pub fn matches_tags(&self, app: &App) -> bool {
+        let has = |tag: &String| app.tags.iter().any(|t| t.to_lowercase() == *tag);
+        self.include_tags.iter().all(has) && !self.exclude_tags.iter().any(has)
+    }
+
+    // Best weighted free-text match across the searched fields; ties go to the
+    // higher weighted field. Without free text every app matches on its name.
+    pub fn match_app(&self, app: &App) -> Option<AppMatch> {
+        if self.text.trim().is_empty() {
+            return Some(AppMatch { field: Field::Name, score: 0, positions: Vec::new() });
+        }
+        let mut best: Option<AppMatch> = None;
+        for (field, weight) in FIELD_WEIGHTS {
+            let Some(text) = field_text(app, field) else { continue };
+            let Some(m) = fuzzy::fuzzy_match(&self.text, &text) else { continue };
+            let score = m.score * weight / 100;
+            if best.as_ref().is_none_or(|b| score > b.score) {
+                best = Some(AppMatch { field, score, positions: m.positions });
+            }
+        }
+        best
+    }
+}
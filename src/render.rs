@@ -0,0 +1,343 @@
+// Double-buffered terminal output.
+//
+// A frame is drawn into a `Buffer` of cells using the same crossterm commands
+// the terminal understands (`draw!(frame, cursor::MoveTo(..), style::..)`).
+// `Renderer::flush` then compares it with the previous frame and queues only
+// the cells that changed, written out with a single flush. Nothing is cleared
+// between frames, so the screen never flickers.
+//
+// Finished frames go to a `Backend`: `Terminal` for the real screen, or
+// `Headless`, which keeps the last frame in memory to be read back as text.
+
+use crossterm::{
+    cursor, queue,
+    style::{self, Attribute, Color},
+    terminal::{self, ClearType},
+};
+use std::io::{self, Write};
+use unicode_width::UnicodeWidthChar;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cell {
+    symbol: char,
+    style: Style,
+    // Right half of a double-width symbol in the cell before; never printed
+    wide_tail: bool,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self { symbol: ' ', style: Style::default(), wide_tail: false }
+    }
+}
+
+// A frame being drawn, with a pen (position and style) like a terminal's cursor
+#[derive(Debug, Clone)]
+pub struct Buffer {
+    width: u16,
+    height: u16,
+    cells: Vec<Cell>,
+    x: u16,
+    y: u16,
+    style: Style,
+}
+
+impl Buffer {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self { width, height, cells: vec![Cell::default(); width as usize * height as usize], x: 0, y: 0, style: Style::default() }
+    }
+
+    fn index(&self, x: u16, y: u16) -> Option<usize> {
+        (x < self.width && y < self.height).then(|| y as usize * self.width as usize + x as usize)
+    }
+
+    pub fn draw(&mut self, command: impl Draw) {
+        command.apply(self);
+    }
+
+    // Writes text at the pen, clipped at the right edge; control characters are skipped
+    pub fn print(&mut self, text: &str) {
+        for ch in text.chars() {
+            let width = ch.width().unwrap_or(0) as u16;
+            if width == 0 {
+                continue;
+            }
+            // A wide symbol that does not fit entirely is dropped, as terminals do
+            if self.x.saturating_add(width) > self.width {
+                self.x = self.width;
+                break;
+            }
+            self.set_cell(self.x, Cell { symbol: ch, style: self.style, wide_tail: false });
+            if width == 2 {
+                self.set_cell(self.x + 1, Cell { symbol: ' ', style: self.style, wide_tail: true });
+            }
+            self.x += width;
+        }
+    }
+
+    // The frame as plain text, one line per row without trailing blanks
+    #[cfg(test)]
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for row in self.cells.chunks(self.width.max(1) as usize) {
+            let line: String = row.iter().filter(|cell| !cell.wide_tail).map(|cell| cell.symbol).collect();
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+
+    // Which cells are styled, lined up with `to_text`: `#` on a background,
+    // `*` bold, `+` in the `accent` color, blank otherwise
+    #[cfg(test)]
+    pub fn to_styles(&self, accent: Color) -> String {
+        let mut text = String::new();
+        for row in self.cells.chunks(self.width.max(1) as usize) {
+            let line: String = row
+                .iter()
+                .filter(|cell| !cell.wide_tail)
+                .map(|cell| match cell.style {
+                    Style { bg: Some(_), .. } => '#',
+                    Style { bold: true, .. } => '*',
+                    Style { fg: Some(fg), .. } if fg == accent => '+',
+                    _ => ' ',
+                })
+                .collect();
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+
+    fn set_cell(&mut self, x: u16, cell: Cell) {
+        let Some(idx) = self.index(x, self.y) else { return };
+        // Overwriting half of a wide symbol blanks its other half
+        if self.cells[idx].wide_tail && x > 0 {
+            self.cells[idx - 1] = Cell { symbol: ' ', ..self.cells[idx - 1] };
+        }
+        if let Some(next) = self.index(x + 1, self.y)
+            && self.cells[next].wide_tail
+            && !cell.wide_tail
+        {
+            self.cells[next] = Cell { wide_tail: false, ..self.cells[next] };
+        }
+        self.cells[idx] = cell;
+    }
+}
+
+// Lets `write!(frame, ...)` print at the pen like it would on stdout
+impl Write for Buffer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        let text = std::str::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.print(text);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// A crossterm command applied to a buffer instead of the terminal
+pub trait Draw {
+    fn apply(self, buffer: &mut Buffer);
+}
+
+impl Draw for cursor::MoveTo {
+    fn apply(self, buffer: &mut Buffer) {
+        buffer.x = self.0;
+        buffer.y = self.1;
+    }
+}
+
+impl Draw for style::SetForegroundColor {
+    fn apply(self, buffer: &mut Buffer) {
+        buffer.style.fg = Some(self.0);
+    }
+}
+
+impl Draw for style::SetBackgroundColor {
+    fn apply(self, buffer: &mut Buffer) {
+        buffer.style.bg = Some(self.0);
+    }
+}
+
+impl Draw for style::ResetColor {
+    fn apply(self, buffer: &mut Buffer) {
+        buffer.style.fg = None;
+        buffer.style.bg = None;
+    }
+}
+
+impl Draw for style::SetAttribute {
+    fn apply(self, buffer: &mut Buffer) {
+        match self.0 {
+            Attribute::Bold => buffer.style.bold = true,
+            Attribute::NormalIntensity => buffer.style.bold = false,
+            Attribute::Reset => buffer.style = Style::default(),
+            _ => {}
+        }
+    }
+}
+
+// `execute!` for a buffer: applies each command in turn
+macro_rules! draw {
+    ($buffer:expr $(, $command:expr)* $(,)?) => {{
+        $( $buffer.draw($command); )*
+    }};
+}
+
+#[derive(Default)]
+pub struct Renderer {
+    previous: Option<Buffer>,
+    cursor: Option<(u16, u16)>,
+}
+
+impl Renderer {
+    // Forget what is on screen, e.g. after another program used the terminal
+    pub fn invalidate(&mut self) {
+        self.previous = None;
+    }
+
+    // Writes the cells that differ from the last frame and places the cursor
+    pub fn flush(&mut self, out: &mut impl Write, frame: Buffer, cursor: Option<(u16, u16)>) -> io::Result<()> {
+        let previous = self.previous.take().filter(|p| p.width == frame.width && p.height == frame.height);
+        // Nothing to write when the frame and caret are what is already on screen
+        if let Some(previous) = previous.as_ref()
+            && previous.cells == frame.cells
+            && self.cursor == cursor
+        {
+            self.previous = Some(frame);
+            return Ok(());
+        }
+        queue!(out, cursor::Hide)?;
+        if previous.is_none() {
+            queue!(out, style::SetAttribute(Attribute::Reset), style::ResetColor, terminal::Clear(ClearType::All))?;
+        }
+
+        let mut pen: Option<(u16, u16)> = None;
+        let mut pen_style: Option<Style> = None;
+        for y in 0..frame.height {
+            for x in 0..frame.width {
+                let idx = y as usize * frame.width as usize + x as usize;
+                let cell = frame.cells[idx];
+                let unchanged = match &previous {
+                    Some(previous) => previous.cells[idx] == cell,
+                    // Freshly cleared screen
+                    None => cell == Cell::default(),
+                };
+                if unchanged || cell.wide_tail {
+                    continue;
+                }
+                if pen != Some((x, y)) {
+                    queue!(out, cursor::MoveTo(x, y))?;
+                }
+                if pen_style != Some(cell.style) {
+                    queue!(out, style::SetAttribute(Attribute::Reset), style::ResetColor)?;
+                    if let Some(fg) = cell.style.fg {
+                        queue!(out, style::SetForegroundColor(fg))?;
+                    }
+                    if let Some(bg) = cell.style.bg {
+                        queue!(out, style::SetBackgroundColor(bg))?;
+                    }
+                    if cell.style.bold {
+                        queue!(out, style::SetAttribute(Attribute::Bold))?;
+                    }
+                    pen_style = Some(cell.style);
+                }
+                queue!(out, style::Print(cell.symbol))?;
+                pen = Some((x + cell.symbol.width().unwrap_or(1) as u16, y));
+            }
+        }
+        queue!(out, style::SetAttribute(Attribute::Reset), style::ResetColor)?;
+
+        if let Some((x, y)) = cursor {
+            queue!(out, cursor::MoveTo(x, y), cursor::Show)?;
+        }
+        out.flush()?;
+        self.previous = Some(frame);
+        self.cursor = cursor;
+        Ok(())
+    }
+}
+
+// Where finished frames are shown
+pub trait Backend {
+    fn size(&self) -> io::Result<(u16, u16)>;
+    fn present(&mut self, frame: Buffer, cursor: Option<(u16, u16)>) -> io::Result<()>;
+    // Forget what is on screen, e.g. after another program used the terminal
+    fn invalidate(&mut self) {}
+}
+
+// The real terminal on stdout
+pub struct Terminal {
+    out: io::Stdout,
+    renderer: Renderer,
+}
+
+impl Default for Terminal {
+    fn default() -> Self {
+        Self { out: io::stdout(), renderer: Renderer::default() }
+    }
+}
+
+impl Backend for Terminal {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        terminal::size()
+    }
+
+    fn present(&mut self, frame: Buffer, cursor: Option<(u16, u16)>) -> io::Result<()> {
+        self.renderer.flush(&mut self.out, frame, cursor)
+    }
+
+    fn invalidate(&mut self) {
+        self.renderer.invalidate();
+    }
+}
+
+// A screen of a fixed size that only remembers the last frame, for rendering without a terminal
+#[cfg(test)]
+pub struct Headless {
+    width: u16,
+    height: u16,
+    frame: Option<Buffer>,
+    cursor: Option<(u16, u16)>,
+}
+
+#[cfg(test)]
+impl Headless {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self { width, height, frame: None, cursor: None }
+    }
+
+    // What the screen shows, with the caret position if it is visible
+    pub fn contents(&self) -> (String, Option<(u16, u16)>) {
+        let text = self.frame.as_ref().map(Buffer::to_text).unwrap_or_default();
+        (text, self.cursor)
+    }
+
+    // The last frame presented, styles included
+    pub fn frame(&self) -> Option<&Buffer> {
+        self.frame.as_ref()
+    }
+}
+
+#[cfg(test)]
+impl Backend for Headless {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        Ok((self.width, self.height))
+    }
+
+    fn present(&mut self, frame: Buffer, cursor: Option<(u16, u16)>) -> io::Result<()> {
+        self.frame = Some(frame);
+        self.cursor = cursor;
+        Ok(())
+    }
+}
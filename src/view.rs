@@ -0,0 +1,1024 @@
+// What the launcher looks like for a given state.
+//
+// `app::State::view` describes the current screen as a `View`, which renders
+// into a `render::Buffer`; nothing here reads input or touches the terminal, so the
+// same frame can be presented on the terminal or kept by `render::Headless`.
+
+use crate::config::{App, Config, LaunchMode, ParseError};
+use crate::keys::{Action, Keymap};
+use crate::render::Buffer;
+use crate::app::{FORM_INPUT_WIDTH, FormState, ListRow, ModalState, PREDEFINED_THEMES, Toast};
+use crate::{launcher, query};
+use crossterm::{cursor, style::{self, Color}};
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+
+// Rows kept visible between the selection and the top or bottom of the list
+const SCROLL_MARGIN: usize = 2;
+
+fn sanitize_for_tui(s: &str) -> String {
+    s.chars().filter(|c| !c.is_control()).collect()
+}
+
+fn format_env(app: &App) -> String {
+    let mut parts = Vec::new();
+    if app.env_clear == Some(true) {
+        parts.push("[cleared]".to_string());
+    }
+    for var in app.env_remove.iter().flatten() {
+        parts.push(format!("-{}", var));
+    }
+    for (k, v) in app.env.iter().flatten() {
+        parts.push(format!("{}={}", k, v));
+    }
+    parts.join(" ")
+}
+
+// Help for the bottom border from the active bindings, cut to fit the pane
+fn border_help(keymap: &Keymap, entries: &[(Action, &str)], width: u16) -> String {
+    let help = keymap.help(entries);
+    if help.is_empty() {
+        return help;
+    }
+    format!(" {} ", help).chars().take(width as usize).collect()
+}
+
+fn parse_color(s: &str) -> Color {
+    match s.to_lowercase().as_str() {
+        "black" => Color::Black,
+        "dark_red" | "darkred" => Color::DarkRed,
+        "dark_green" | "darkgreen" => Color::DarkGreen,
+        "dark_yellow" | "darkyellow" => Color::DarkYellow,
+        "dark_blue" | "darkblue" => Color::DarkBlue,
+        "dark_magenta" | "darkmagenta" => Color::DarkMagenta,
+        "dark_cyan" | "darkcyan" => Color::DarkCyan,
+        "grey" => Color::Grey,
+        "dark_grey" | "darkgrey" => Color::DarkGrey,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "white" => Color::White,
+        _ => Color::Reset,
+    }
+}
+
+pub struct ActiveTheme {
+    pub border: Color,
+    pub accent: Color,
+    pub text: Color,
+    pub dim: Color,
+    pub title: Color,
+}
+
+impl ActiveTheme {
+    pub fn from_config(config: &Config) -> Self {
+        let border = config.theme.as_ref().and_then(|t| t.border_color.as_ref()).map(|s| parse_color(s)).unwrap_or(Color::Blue);
+        let accent = config.theme.as_ref().and_then(|t| t.accent_color.as_ref()).map(|s| parse_color(s)).unwrap_or(Color::Cyan);
+        let text = config.theme.as_ref().and_then(|t| t.text_color.as_ref()).map(|s| parse_color(s)).unwrap_or(Color::White);
+        let dim = config.theme.as_ref().and_then(|t| t.dim_color.as_ref()).map(|s| parse_color(s)).unwrap_or(Color::DarkGrey);
+        let title = Color::Yellow;
+        Self { border, accent, text, dim, title }
+    }
+}
+
+// Where everything goes on a terminal of a given size; also used to map mouse clicks back
+#[derive(Debug, Clone, Copy)]
+pub struct Layout {
+    pub term_cols: u16,
+    pub term_rows: u16,
+    pub start_x: u16,
+    pub start_y: u16,
+    pub box_width: u16,
+    pub box_height: u16,
+    pub left_pane_width: u16,
+    pub right_pane_width: u16,
+    pub divider_x: u16,
+    pub search_y: u16,
+    // The list starts below the search divider
+    pub content_start_y: u16,
+    pub max_items: usize,
+}
+
+impl Layout {
+    pub fn new(term_cols: u16, term_rows: u16) -> Self {
+        // Box Width: at least 90, max 80% of screen
+        let box_width = std::cmp::max(90, (term_cols as f32 * 0.8) as u16);
+        let box_height = std::cmp::max(12, (term_rows as f32 * 0.6) as u16);
+
+        let start_x = (term_cols.saturating_sub(box_width)) / 2;
+        let start_y = (term_rows.saturating_sub(box_height)) / 2;
+
+        let inner_width = box_width.saturating_sub(2);
+        let left_pane_width = (inner_width as f32 * 0.55) as u16;
+        let right_pane_width = inner_width.saturating_sub(left_pane_width).saturating_sub(1);
+        let divider_x = start_x + 1 + left_pane_width;
+
+        Self {
+            term_cols,
+            term_rows,
+            start_x,
+            start_y,
+            box_width,
+            box_height,
+            left_pane_width,
+            right_pane_width,
+            divider_x,
+            search_y: start_y + 2,
+            content_start_y: start_y + 4,
+            max_items: box_height.saturating_sub(6) as usize,
+        }
+    }
+
+    // Rows kept between the selection and the edges, less on short lists
+    pub fn margin(&self) -> usize {
+        SCROLL_MARGIN.min(self.max_items.saturating_sub(1) / 2)
+    }
+
+    // First row to show: the viewport only scrolls once the selection comes within the margin of an edge
+    pub fn scroll(&self, selected: usize, scroll_offset: usize, rows: usize) -> usize {
+        let margin = self.margin();
+        let mut scroll_offset = scroll_offset;
+        if selected < scroll_offset + margin {
+            scroll_offset = selected.saturating_sub(margin);
+        } else if selected + margin >= scroll_offset + self.max_items {
+            scroll_offset = (selected + margin + 1).saturating_sub(self.max_items);
+        }
+        scroll_offset.min(rows.saturating_sub(self.max_items))
+    }
+
+    // Position and size (x, y, width, height) of the dialog shown in a state, centered on screen
+    pub fn modal_rect(&self, state: &ModalState, form_fields: usize) -> Option<(u16, u16, u16, u16)> {
+        let (width, height) = match state {
+            ModalState::None => return None,
+            ModalState::Form => (60, form_fields as u16 + 6),
+            ModalState::DeleteConfirm => (50, 8),
+            ModalState::ThemeSelect => (44, PREDEFINED_THEMES.len() as u16 + 6),
+            ModalState::SaveConflict => (60, 9),
+        };
+        Some(((self.term_cols.saturating_sub(width)) / 2, (self.term_rows.saturating_sub(height)) / 2, width, height))
+    }
+}
+
+// Everything a frame shows, borrowed from the event loop's state
+pub struct View<'a> {
+    pub config: &'a Config,
+    pub keymap: &'a Keymap,
+    pub unsaved: bool,
+    pub ranked: &'a [(&'a App, query::AppMatch, u64)],
+    pub rows: &'a [ListRow<'a>],
+    pub collapsed: &'a BTreeSet<String>,
+    pub selected: usize,
+    pub scroll_offset: usize,
+    pub search_query: &'a str,
+    pub search_active: bool,
+    pub search_cursor_pos: usize,
+    // The vim `:` line while it is being typed
+    pub command_line: Option<&'a str>,
+    pub modal_state: &'a ModalState,
+    pub form: Option<&'a FormState>,
+    pub selected_theme_idx: usize,
+    pub conflict_note: Option<&'a str>,
+    pub toast: Option<&'a Toast>,
+    pub chord: &'a str,
+    pub leader_pending: bool,
+}
+
+impl View<'_> {
+    // Draws the whole screen and returns where the caret goes, if it is shown
+    pub fn render(&self, layout: &Layout, frame: &mut Buffer) -> io::Result<Option<(u16, u16)>> {
+        let theme = ActiveTheme::from_config(self.config);
+        self.draw_frame(layout, &theme, frame)?;
+        self.draw_search_bar(layout, &theme, frame)?;
+        self.draw_list(layout, &theme, frame)?;
+        self.draw_details(layout, &theme, frame)?;
+        match self.modal_state {
+            ModalState::Form => self.draw_form(layout, &theme, frame)?,
+            ModalState::DeleteConfirm => self.draw_delete_confirm(layout, frame)?,
+            ModalState::ThemeSelect => self.draw_theme_select(layout, &theme, frame)?,
+            ModalState::SaveConflict => self.draw_save_conflict(layout, &theme, frame)?,
+            ModalState::None => {
+                if !self.chord.is_empty() || self.leader_pending {
+                    self.draw_which_key(layout, &theme, frame)?;
+                }
+            }
+        }
+        Ok(self.cursor(layout))
+    }
+
+    fn selected_app(&self) -> Option<(&App, &query::AppMatch)> {
+        match self.rows.get(self.selected) {
+            Some(ListRow::App(i)) => Some((self.ranked[*i].0, &self.ranked[*i].1)),
+            _ => None,
+        }
+    }
+
+    // Border, title, pane divider, key help and the toast under the box
+    fn draw_frame(&self, layout: &Layout, theme: &ActiveTheme, frame: &mut Buffer) -> io::Result<()> {
+        let Layout { term_rows, start_x, start_y, box_width, box_height, left_pane_width, right_pane_width, divider_x, .. } = *layout;
+
+        // Draw Border
+        draw!(frame, style::SetForegroundColor(theme.border));
+
+        // Top
+        let left_top_dashes = "─".repeat(left_pane_width as usize);
+        let right_top_dashes = "─".repeat(right_pane_width as usize);
+        draw!(frame, cursor::MoveTo(start_x, start_y));
+        write!(frame, "╭{}┬{}╮", left_top_dashes, right_top_dashes)?;
+
+        // Sides and divider background
+        let left_spaces = " ".repeat(left_pane_width as usize);
+        let right_spaces = " ".repeat(right_pane_width as usize);
+        for i in 1..box_height.saturating_sub(1) {
+            draw!(frame, cursor::MoveTo(start_x, start_y + i));
+            write!(frame, "│{}│{}│", left_spaces, right_spaces)?;
+        }
+
+        // Bottom
+        let left_bottom_dashes = "─".repeat(left_pane_width as usize);
+        let right_bottom_dashes = "─".repeat(right_pane_width as usize);
+        draw!(frame, cursor::MoveTo(start_x, start_y + box_height - 1));
+        write!(frame, "╰{}┴{}╯", left_bottom_dashes, right_bottom_dashes)?;
+
+        draw!(frame, style::ResetColor);
+
+        // Title
+        let title = if self.unsaved { " Term Launcher (unsaved) " } else { " Term Launcher " };
+        let title_start_x = start_x + 1 + (left_pane_width.saturating_sub(title.len() as u16)) / 2;
+        draw!(frame, cursor::MoveTo(title_start_x, start_y), style::SetForegroundColor(theme.title), style::SetAttribute(style::Attribute::Bold));
+        write!(frame, "{}", title)?;
+        draw!(frame, style::ResetColor);
+
+        // Draw horizontal divider under search bar
+        draw!(frame, style::SetForegroundColor(theme.border));
+        draw!(frame, cursor::MoveTo(start_x, start_y + 3));
+        write!(frame, "├{}┼", "─".repeat(left_pane_width as usize))?;
+        draw!(frame, style::ResetColor);
+
+        // Help Text (Left bottom border)
+        let left_help = border_help(self.keymap, &[(Action::Add, "Add"), (Action::Delete, "Del"), (Action::Edit, "Edit"), (Action::Search, "Search")], left_pane_width);
+        let left_help_x = start_x + 1 + (left_pane_width.saturating_sub(left_help.chars().count() as u16)) / 2;
+        draw!(frame, cursor::MoveTo(left_help_x, start_y + box_height - 1), style::SetForegroundColor(theme.dim));
+        write!(frame, "{}", left_help)?;
+
+        // Help Text (Right bottom border)
+        let right_help = border_help(self.keymap, &[(Action::Theme, "Theme"), (Action::Quit, "Quit")], right_pane_width);
+        let right_help_x = divider_x + 1 + (right_pane_width.saturating_sub(right_help.chars().count() as u16)) / 2;
+        draw!(frame, cursor::MoveTo(right_help_x, start_y + box_height - 1), style::SetForegroundColor(theme.dim));
+        write!(frame, "{}", right_help)?;
+        draw!(frame, style::ResetColor);
+
+        // Toast under the launcher box
+        if let Some(toast) = self.toast
+            && start_y + box_height < term_rows
+        {
+            let message: String = sanitize_for_tui(&toast.message).chars().take(box_width as usize).collect();
+            let toast_x = start_x + (box_width.saturating_sub(message.chars().count() as u16)) / 2;
+            let color = if toast.is_error { Color::Red } else { theme.accent };
+            draw!(frame, cursor::MoveTo(toast_x, start_y + box_height), style::SetForegroundColor(color));
+            write!(frame, "{}", message)?;
+            draw!(frame, style::ResetColor);
+        }
+        Ok(())
+    }
+
+    fn draw_search_bar(&self, layout: &Layout, theme: &ActiveTheme, frame: &mut Buffer) -> io::Result<()> {
+        let Layout { start_x, left_pane_width, search_y, .. } = *layout;
+
+        let search_label = " 🔎 Search: ";
+        draw!(frame, cursor::MoveTo(start_x + 2, search_y));
+        if self.search_active {
+            draw!(frame, style::SetForegroundColor(theme.accent), style::SetAttribute(style::Attribute::Bold));
+        } else {
+            draw!(frame, style::SetForegroundColor(theme.title));
+        }
+        write!(frame, "{}", search_label)?;
+        draw!(frame, style::ResetColor);
+
+        // Search Input box
+        draw!(frame, cursor::MoveTo(start_x + 12, search_y));
+        if self.search_active {
+            draw!(frame, style::SetForegroundColor(theme.accent));
+        } else {
+            draw!(frame, style::SetForegroundColor(theme.dim));
+        }
+        write!(frame, "[")?;
+
+        draw!(frame, cursor::MoveTo(start_x + 13, search_y), style::SetForegroundColor(theme.text));
+        let search_inner_width = left_pane_width.saturating_sub(15);
+        let display_search: String = self.search_query.chars().take(search_inner_width as usize).collect();
+        write!(frame, "{}", display_search)?;
+        // Pad spaces
+        let spaces = search_inner_width.saturating_sub(display_search.chars().count() as u16);
+        write!(frame, "{}", " ".repeat(spaces as usize))?;
+
+        if self.search_active {
+            draw!(frame, style::SetForegroundColor(theme.accent));
+        } else {
+            draw!(frame, style::SetForegroundColor(theme.dim));
+        }
+        write!(frame, "]")?;
+        draw!(frame, style::ResetColor);
+
+        // Vim command line takes over the search row while typed
+        if let Some(command_line) = self.command_line {
+            let line: String = format!(":{}", sanitize_for_tui(command_line)).chars().take(left_pane_width.saturating_sub(2) as usize).collect();
+            draw!(frame, cursor::MoveTo(start_x + 1, search_y));
+            write!(frame, "{}", " ".repeat(left_pane_width as usize))?;
+            draw!(frame, cursor::MoveTo(start_x + 2, search_y), style::SetForegroundColor(theme.accent));
+            write!(frame, "{}", line)?;
+            draw!(frame, style::ResetColor);
+        }
+        Ok(())
+    }
+
+    // The rows in the viewport, with a scrollbar when they do not all fit
+    fn draw_list(&self, layout: &Layout, theme: &ActiveTheme, frame: &mut Buffer) -> io::Result<()> {
+        let Layout { start_x, left_pane_width, divider_x, content_start_y, max_items, .. } = *layout;
+        let rows = self.rows;
+        let start_index = self.scroll_offset.min(rows.len());
+        let end_index = std::cmp::min(rows.len(), start_index + max_items);
+
+        if rows.is_empty() {
+             let msg = "No apps found.";
+             let msg_x = start_x + 1 + (left_pane_width.saturating_sub(msg.len() as u16)) / 2;
+             draw!(frame, cursor::MoveTo(msg_x, content_start_y), style::SetForegroundColor(theme.dim));
+             write!(frame, "{}", msg)?;
+             draw!(frame, style::ResetColor);
+        }
+
+        for (i, list_row) in rows[start_index..end_index].iter().enumerate() {
+            let actual_idx = start_index + i;
+            let row = content_start_y + i as u16;
+
+            let (app, positions) = match list_row {
+                // Only name matches can be marked here; others show in the details pane
+                ListRow::App(idx) if self.ranked[*idx].1.field == query::Field::Name => (self.ranked[*idx].0, self.ranked[*idx].1.positions.as_slice()),
+                ListRow::App(idx) => (self.ranked[*idx].0, &[][..]),
+                ListRow::Header { group, count } => {
+                    let marker = if self.collapsed.contains(*group) { '▸' } else { '▾' };
+                    let mut header = format!("{} {} ({})", marker, sanitize_for_tui(group), count);
+                    if actual_idx == self.selected {
+                        header = format!("> {} <", header);
+                        draw!(frame, style::SetForegroundColor(Color::Black), style::SetBackgroundColor(theme.accent));
+                    } else {
+                        draw!(frame, style::SetForegroundColor(theme.title), style::SetAttribute(style::Attribute::Bold));
+                    }
+                    let header_x = start_x + 1 + (left_pane_width.saturating_sub(header.chars().count() as u16)) / 2;
+                    draw!(frame, cursor::MoveTo(header_x, row));
+                    write!(frame, "{}", header)?;
+                    draw!(frame, style::SetAttribute(style::Attribute::Reset), style::ResetColor);
+                    continue;
+                }
+            };
+
+            // Format name and key
+            let key_str = format!("({})", sanitize_for_tui(&app.key));
+            let name_str = sanitize_for_tui(&app.name);
+
+            let line_start_x = start_x + 1 + (left_pane_width.saturating_sub((name_str.len() + 1 + key_str.len()) as u16)) / 2;
+
+            if actual_idx == self.selected {
+                // Selected: highlight with accent background
+                let line = format!("{} {}", name_str, key_str);
+                let marked_line = format!("> {} <", line);
+                let marked_start_x = start_x + 1 + (left_pane_width.saturating_sub(marked_line.len() as u16)) / 2;
+
+                draw!(frame, cursor::MoveTo(marked_start_x, row), style::SetForegroundColor(Color::Black), style::SetBackgroundColor(theme.accent));
+                write!(frame, "{}", marked_line)?;
+                draw!(frame, style::ResetColor);
+            } else {
+                // Not selected: highlight every fuzzy-matched character
+                draw!(frame, cursor::MoveTo(line_start_x, row));
+                for (ci, ch) in name_str.chars().enumerate() {
+                    if positions.contains(&ci) {
+                        draw!(frame, style::SetForegroundColor(theme.accent), style::SetAttribute(style::Attribute::Bold));
+                    } else {
+                        draw!(frame, style::SetForegroundColor(theme.text), style::SetAttribute(style::Attribute::NormalIntensity));
+                    }
+                    write!(frame, "{}", ch)?;
+                }
+                draw!(frame, style::SetAttribute(style::Attribute::Reset));
+
+                draw!(frame, style::SetForegroundColor(theme.dim));
+                write!(frame, " {}", key_str)?;
+                draw!(frame, style::ResetColor);
+            }
+        }
+
+        // Scrollbar in the last column of the list when it does not fit
+        if rows.len() > max_items && max_items > 0 {
+            let thumb_len = (max_items * max_items / rows.len()).max(1);
+            let thumb_start = start_index * (max_items - thumb_len) / (rows.len() - max_items);
+            for i in 0..max_items {
+                let on_thumb = (thumb_start..thumb_start + thumb_len).contains(&i);
+                let (ch, color) = if on_thumb { ("┃", theme.accent) } else { ("│", theme.dim) };
+                draw!(frame, cursor::MoveTo(divider_x - 1, content_start_y + i as u16), style::SetForegroundColor(color));
+                write!(frame, "{}", ch)?;
+            }
+            draw!(frame, style::ResetColor);
+        }
+        Ok(())
+    }
+
+    // Right pane: the selected group or app
+    fn draw_details(&self, layout: &Layout, theme: &ActiveTheme, frame: &mut Buffer) -> io::Result<()> {
+        let Layout { start_y, box_height, right_pane_width, divider_x, content_start_y, .. } = *layout;
+
+        if let Some(ListRow::Header { group, count }) = self.rows.get(self.selected) {
+            let details_title = " Group ";
+            let details_title_x = divider_x + 1 + (right_pane_width.saturating_sub(details_title.len() as u16)) / 2;
+            draw!(frame, cursor::MoveTo(details_title_x, start_y + 1), style::SetForegroundColor(theme.accent), style::SetAttribute(style::Attribute::Bold));
+            write!(frame, "{}", details_title)?;
+            draw!(frame, style::ResetColor);
+
+            let state = if self.collapsed.contains(*group) { "Collapsed" } else { "Expanded" };
+            let lines = [
+                ("Name", sanitize_for_tui(group), theme.text),
+                ("Apps", count.to_string(), theme.text),
+                ("State", state.to_string(), theme.text),
+                ("Keys", self.keymap.help(&[(Action::Launch, "fold"), (Action::NextGroup, "next")]), theme.dim),
+                ("", self.keymap.help(&[(Action::FoldAll, "fold all")]), theme.dim),
+            ];
+            for (offset, (label, value, color)) in lines.iter().enumerate() {
+                let label_part = if label.is_empty() { "      ".to_string() } else { format!("{}: ", label) };
+                let value: String = value.chars().take((right_pane_width.saturating_sub(4) as usize).saturating_sub(label_part.len())).collect();
+                draw!(frame, cursor::MoveTo(divider_x + 2, content_start_y + offset as u16), style::SetForegroundColor(theme.title));
+                write!(frame, "{}", label_part)?;
+                draw!(frame, style::SetForegroundColor(*color));
+                write!(frame, "{}", value)?;
+            }
+            draw!(frame, style::ResetColor);
+        }
+
+        let Some((app, selected_match)) = self.selected_app() else { return Ok(()) };
+        let right_x = divider_x + 2;
+        let inner_r_width = right_pane_width.saturating_sub(4) as usize;
+
+        let mut r_row = content_start_y;
+
+        // 1. Draw Title
+        let details_title = " App Details ";
+        let details_title_x = divider_x + 1 + (right_pane_width.saturating_sub(details_title.len() as u16)) / 2;
+        draw!(frame, cursor::MoveTo(details_title_x, start_y + 1), style::SetForegroundColor(theme.accent), style::SetAttribute(style::Attribute::Bold));
+        write!(frame, "{}", details_title)?;
+        draw!(frame, style::ResetColor);
+
+        // Characters the search matched in a field, if the match came from it
+        let matched = |field: query::Field| -> &[usize] {
+            if selected_match.field == field { &selected_match.positions } else { &[] }
+        };
+
+        // A line with search hits gets an accent label and its matched characters marked
+        let mut draw_detail_line = |frame: &mut Buffer, label: &str, value: &str, label_color: Color, val_color: Color, highlight: &[usize]| -> io::Result<()> {
+            if r_row >= start_y + box_height - 1 {
+                return Ok(());
+            }
+            draw!(frame, cursor::MoveTo(right_x, r_row));
+
+            let label_part = format!("{}: ", label);
+            let available_val_width = inner_r_width.saturating_sub(label_part.len());
+            let label_color = if highlight.is_empty() { label_color } else { theme.accent };
+
+            draw!(frame, style::SetForegroundColor(label_color));
+            write!(frame, "{}", label_part)?;
+            draw!(frame, style::SetForegroundColor(val_color));
+            for (ci, ch) in value.chars().take(available_val_width).enumerate() {
+                if highlight.contains(&ci) {
+                    draw!(frame, style::SetForegroundColor(theme.accent), style::SetAttribute(style::Attribute::Bold));
+                    write!(frame, "{}", ch)?;
+                    draw!(frame, style::SetForegroundColor(val_color), style::SetAttribute(style::Attribute::NormalIntensity));
+                } else {
+                    write!(frame, "{}", ch)?;
+                }
+            }
+            draw!(frame, style::SetAttribute(style::Attribute::Reset), style::ResetColor);
+
+            r_row += 1;
+            Ok(())
+        };
+
+        // 2. Name
+        let name_str = query::field_text(app, query::Field::Name).unwrap_or_default();
+        draw_detail_line(frame, "Name", &name_str, theme.title, theme.text, matched(query::Field::Name))?;
+
+        // 3. Hotkey
+        draw_detail_line(frame, "Hotkey", &app.key, theme.title, theme.text, &[])?;
+
+        // 4. Command
+        let cmd_str = query::field_text(app, query::Field::Command).unwrap_or_default();
+        draw_detail_line(frame, "Command", &cmd_str, theme.title, theme.text, matched(query::Field::Command))?;
+
+        // 5. Resolved Path
+        let path_resolved = launcher::resolve_command(&app.cmd, self.config.extra_allowed_dirs());
+        let (path_str, path_color) = if let Some(path) = path_resolved {
+            (path.to_string_lossy().into_owned(), Color::Green)
+        } else {
+            ("Not found / Blocked".to_string(), Color::Red)
+        };
+        draw_detail_line(frame, "Resolved", &path_str, theme.title, path_color, &[])?;
+
+        // 6. Arguments
+        let args_str = query::field_text(app, query::Field::Args).unwrap_or_else(|| "None".to_string());
+        draw_detail_line(frame, "Args", &args_str, theme.title, theme.text, matched(query::Field::Args))?;
+
+        // 7. Description
+        let desc_str = query::field_text(app, query::Field::Description).unwrap_or_else(|| "No description provided".to_string());
+        let desc_color = if app.description.is_some() { theme.text } else { theme.dim };
+        draw_detail_line(frame, "Desc", &desc_str, theme.title, desc_color, matched(query::Field::Description))?;
+
+        if let Some(group) = app.group() {
+            draw_detail_line(frame, "Group", group, theme.title, theme.text, &[])?;
+        }
+        if let Some(tags_str) = query::field_text(app, query::Field::Tags) {
+            draw_detail_line(frame, "Tags", &tags_str, theme.title, theme.text, matched(query::Field::Tags))?;
+        }
+
+        // 8. Working directory
+        let (cwd_str, cwd_color) = match &app.cwd {
+            Some(cwd) => (cwd.as_str(), theme.text),
+            None => ("Inherited", theme.dim),
+        };
+        draw_detail_line(frame, "Cwd", cwd_str, theme.title, cwd_color, &[])?;
+
+        // 9. Environment
+        let env_str = format_env(app);
+        let (env_str, env_color) = if env_str.is_empty() {
+            ("Inherited".to_string(), theme.dim)
+        } else {
+            (env_str, theme.text)
+        };
+        draw_detail_line(frame, "Env", &env_str, theme.title, env_color, &[])?;
+
+        // 10. Launch mode, when not the usual foreground
+        match app.mode {
+            Some(mode) if mode != LaunchMode::Foreground => draw_detail_line(frame, "Mode", mode.name(), theme.title, theme.text, &[]),
+            _ => Ok(()),
+        }
+    }
+
+    fn draw_form(&self, layout: &Layout, theme: &ActiveTheme, frame: &mut Buffer) -> io::Result<()> {
+        let Some(form) = self.form else { return Ok(()) };
+        let Some((modal_x, modal_y, modal_width, modal_height)) = layout.modal_rect(self.modal_state, form.fields.len()) else { return Ok(()) };
+        draw_modal_box(frame, (modal_x, modal_y, modal_width, modal_height), form.title, theme.accent)?;
+
+        // Draw fields
+        for (idx, field) in form.fields.iter().enumerate() {
+            let field_y = modal_y + 2 + idx as u16;
+
+            // Label
+            draw!(frame, cursor::MoveTo(modal_x + 3, field_y));
+            if idx == form.active_field {
+                draw!(frame, style::SetForegroundColor(theme.accent), style::SetAttribute(style::Attribute::Bold));
+            } else {
+                draw!(frame, style::SetForegroundColor(theme.title));
+            }
+            write!(frame, "{:11}", field.label)?;
+            draw!(frame, style::ResetColor);
+
+            // Input bracket
+            draw!(frame, cursor::MoveTo(modal_x + 15, field_y));
+            if idx == form.active_field {
+                draw!(frame, style::SetForegroundColor(theme.accent));
+            } else {
+                draw!(frame, style::SetForegroundColor(theme.dim));
+            }
+            write!(frame, "[")?;
+
+            // Value
+            draw!(frame, cursor::MoveTo(modal_x + 16, field_y), style::SetForegroundColor(theme.text));
+            let val_limit = FORM_INPUT_WIDTH;
+            let display_val: String = field.value.chars().skip(field.scroll()).take(val_limit).collect();
+            write!(frame, "{}", display_val)?;
+
+            // Fill remaining input box space
+            let spaces = val_limit.saturating_sub(display_val.chars().count());
+            write!(frame, "{}", " ".repeat(spaces))?;
+
+            // Close bracket
+            if idx == form.active_field {
+                draw!(frame, style::SetForegroundColor(theme.accent));
+            } else {
+                draw!(frame, style::SetForegroundColor(theme.dim));
+            }
+            write!(frame, "]")?;
+            draw!(frame, style::ResetColor);
+        }
+
+        // Draw buttons/help in modal
+        let form_help = " [Enter] Save   [Esc] Cancel   [Tab] Next ";
+        let form_help_x = modal_x + (modal_width.saturating_sub(form_help.len() as u16)) / 2;
+        draw!(frame, cursor::MoveTo(form_help_x, modal_y + modal_height - 3), style::SetForegroundColor(theme.dim));
+        write!(frame, "{}", form_help)?;
+        draw!(frame, style::ResetColor);
+
+        // Draw error message if any
+        if let Some(ref err) = form.error_message {
+            let err_display = format!("Error: {}", err);
+            let err_x = modal_x + (modal_width.saturating_sub(err_display.len() as u16)) / 2;
+            draw!(frame, cursor::MoveTo(err_x, modal_y + modal_height - 2), style::SetForegroundColor(Color::Red), style::SetAttribute(style::Attribute::Bold));
+            write!(frame, "{}", err_display)?;
+            draw!(frame, style::ResetColor);
+        }
+        Ok(())
+    }
+
+    fn draw_delete_confirm(&self, layout: &Layout, frame: &mut Buffer) -> io::Result<()> {
+        let Some((app, _)) = self.selected_app() else { return Ok(()) };
+        let Some((modal_x, modal_y, modal_width, modal_height)) = layout.modal_rect(self.modal_state, 0) else { return Ok(()) };
+        draw_modal_box(frame, (modal_x, modal_y, modal_width, modal_height), "Confirm Delete", Color::Red)?;
+
+        // Message
+        let msg1 = "Are you sure you want to delete";
+        let msg2 = format!("'{}'?", app.name);
+        let msg1_x = modal_x + (modal_width.saturating_sub(msg1.len() as u16)) / 2;
+        let msg2_x = modal_x + (modal_width.saturating_sub(msg2.len() as u16)) / 2;
+
+        draw!(frame, cursor::MoveTo(msg1_x, modal_y + 2), style::SetForegroundColor(Color::White));
+        write!(frame, "{}", msg1)?;
+        draw!(frame, cursor::MoveTo(msg2_x, modal_y + 3), style::SetForegroundColor(Color::Yellow));
+        write!(frame, "{}", msg2)?;
+
+        // Buttons
+        let btn_help = " [y] Yes      [n/Esc] No ";
+        let btn_x = modal_x + (modal_width.saturating_sub(btn_help.len() as u16)) / 2;
+        draw!(frame, cursor::MoveTo(btn_x, modal_y + 5), style::SetForegroundColor(Color::White));
+        write!(frame, "{}", btn_help)?;
+        draw!(frame, style::ResetColor);
+        Ok(())
+    }
+
+    fn draw_theme_select(&self, layout: &Layout, theme: &ActiveTheme, frame: &mut Buffer) -> io::Result<()> {
+        let Some((modal_x, modal_y, modal_width, modal_height)) = layout.modal_rect(self.modal_state, 0) else { return Ok(()) };
+        draw_modal_box(frame, (modal_x, modal_y, modal_width, modal_height), "Select Theme", theme.accent)?;
+
+        // Draw themes list
+        for (idx, predefined) in PREDEFINED_THEMES.iter().enumerate() {
+            let theme_y = modal_y + 2 + idx as u16;
+
+            // Format the theme name to fit and center in modal
+            let display_name = format!("  {}  ", predefined.name);
+            let text_x = modal_x + (modal_width.saturating_sub(display_name.len() as u16)) / 2;
+            draw!(frame, cursor::MoveTo(text_x, theme_y));
+
+            if idx == self.selected_theme_idx {
+                draw!(frame, style::SetForegroundColor(Color::Black), style::SetBackgroundColor(theme.accent));
+                write!(frame, " {} ", predefined.name)?;
+                draw!(frame, style::ResetColor);
+            } else {
+                draw!(frame, style::SetForegroundColor(theme.text));
+                write!(frame, "  {}  ", predefined.name)?;
+                draw!(frame, style::ResetColor);
+            }
+        }
+
+        // Draw help/instructions at the bottom
+        let theme_help = " [Up/Down] Navigate  [Enter] Select  [Esc] Cancel ";
+        let theme_help_x = modal_x + (modal_width.saturating_sub(theme_help.len() as u16)) / 2;
+        draw!(frame, cursor::MoveTo(theme_help_x, modal_y + modal_height - 3), style::SetForegroundColor(theme.dim));
+        write!(frame, "{}", theme_help)?;
+        draw!(frame, style::ResetColor);
+        Ok(())
+    }
+
+    fn draw_save_conflict(&self, layout: &Layout, theme: &ActiveTheme, frame: &mut Buffer) -> io::Result<()> {
+        let Some((modal_x, modal_y, modal_width, modal_height)) = layout.modal_rect(self.modal_state, 0) else { return Ok(()) };
+        draw_modal_box(frame, (modal_x, modal_y, modal_width, modal_height), "Config Changed On Disk", Color::Yellow)?;
+
+        // Message
+        let msg1 = "Another program edited the config file.";
+        let msg2 = "Your change has not been saved yet.";
+        let msg1_x = modal_x + (modal_width.saturating_sub(msg1.len() as u16)) / 2;
+        let msg2_x = modal_x + (modal_width.saturating_sub(msg2.len() as u16)) / 2;
+        draw!(frame, cursor::MoveTo(msg1_x, modal_y + 2), style::SetForegroundColor(Color::White));
+        write!(frame, "{}", msg1)?;
+        draw!(frame, cursor::MoveTo(msg2_x, modal_y + 3));
+        write!(frame, "{}", msg2)?;
+
+        // Buttons
+        let btn_help = " [r] Reload  [o] Overwrite  [m] Merge  [Esc] Later ";
+        let btn_x = modal_x + (modal_width.saturating_sub(btn_help.len() as u16)) / 2;
+        draw!(frame, cursor::MoveTo(btn_x, modal_y + 5), style::SetForegroundColor(theme.accent));
+        write!(frame, "{}", btn_help)?;
+
+        if let Some(note) = self.conflict_note {
+            let note: String = sanitize_for_tui(note).chars().take((modal_width - 4) as usize).collect();
+            draw!(frame, cursor::MoveTo(modal_x + 2, modal_y + 6), style::SetForegroundColor(Color::Red));
+            write!(frame, "{}", note)?;
+        }
+        draw!(frame, style::ResetColor);
+        Ok(())
+    }
+
+    // Which-key overlay listing the completions of a pending sequence
+    fn draw_which_key(&self, layout: &Layout, theme: &ActiveTheme, frame: &mut Buffer) -> io::Result<()> {
+        let Layout { start_x, start_y, box_height, left_pane_width, .. } = *layout;
+        let chord = self.chord;
+        let completions: Vec<&App> = self.config.apps.iter().filter(|a| a.key.starts_with(chord)).collect();
+        let modal_width: u16 = 40;
+        let max_lines = box_height.saturating_sub(6) as usize;
+        let shown = completions.len().min(max_lines);
+        let modal_height = shown as u16 + 4;
+        let modal_x = start_x + 1 + (left_pane_width.saturating_sub(modal_width)) / 2;
+        let modal_y = (start_y + box_height).saturating_sub(modal_height + 1);
+
+        draw!(frame, style::SetForegroundColor(theme.accent));
+        let leader = self.keymap.leader_label().filter(|_| self.leader_pending).map(|l| format!("{} ", l)).unwrap_or_default();
+        let title = format!(" {}{}… ", leader, sanitize_for_tui(chord));
+        let title_len = title.chars().count();
+        let dash_len = (modal_width as usize - 2).saturating_sub(title_len) / 2;
+        let right_dashes = (modal_width as usize - 2).saturating_sub(title_len + dash_len);
+        draw!(frame, cursor::MoveTo(modal_x, modal_y));
+        write!(frame, "╭{}{}{}╮", "─".repeat(dash_len), title, "─".repeat(right_dashes))?;
+        for r in 1..modal_height - 1 {
+            draw!(frame, cursor::MoveTo(modal_x, modal_y + r));
+            write!(frame, "│{}│", " ".repeat((modal_width - 2) as usize))?;
+        }
+        draw!(frame, cursor::MoveTo(modal_x, modal_y + modal_height - 1));
+        write!(frame, "╰{}╯", "─".repeat((modal_width - 2) as usize))?;
+
+        for (idx, app) in completions.iter().take(shown).enumerate() {
+            // An exact match that is also a prefix fires when the wait runs out
+            let rest = if app.key == chord { "⏎".to_string() } else { sanitize_for_tui(&app.key[chord.len()..]) };
+            let name: String = sanitize_for_tui(&app.name).chars().take(modal_width as usize - 12).collect();
+            draw!(frame, cursor::MoveTo(modal_x + 2, modal_y + 1 + idx as u16), style::SetForegroundColor(theme.accent), style::SetAttribute(style::Attribute::Bold));
+            write!(frame, "{:>6}", rest)?;
+            draw!(frame, style::SetAttribute(style::Attribute::NormalIntensity), style::SetForegroundColor(theme.dim));
+            write!(frame, " → ")?;
+            draw!(frame, style::SetForegroundColor(theme.text));
+            write!(frame, "{}", name)?;
+        }
+
+        let chord_help = if completions.len() > shown { format!(" +{} more  [Esc] Cancel ", completions.len() - shown) } else { " [Esc] Cancel ".to_string() };
+        let help_x = modal_x + (modal_width.saturating_sub(chord_help.len() as u16)) / 2;
+        draw!(frame, cursor::MoveTo(help_x, modal_y + modal_height - 2), style::SetForegroundColor(theme.dim));
+        write!(frame, "{}", chord_help)?;
+        draw!(frame, style::SetAttribute(style::Attribute::Reset), style::ResetColor);
+        Ok(())
+    }
+
+    // Caret in the search box, the command line or the focused form field
+    fn cursor(&self, layout: &Layout) -> Option<(u16, u16)> {
+        if self.search_active {
+            return Some((layout.start_x + 13 + self.search_cursor_pos as u16, layout.search_y));
+        }
+        if let Some(command_line) = self.command_line {
+            return Some(((layout.start_x + 3 + command_line.chars().count() as u16).min(layout.start_x + layout.left_pane_width), layout.search_y));
+        }
+        if *self.modal_state == ModalState::Form
+            && let Some(form) = self.form
+            && let Some((modal_x, modal_y, _, _)) = layout.modal_rect(self.modal_state, form.fields.len())
+        {
+            let field = &form.fields[form.active_field];
+            return Some((modal_x + 16 + (field.cursor_pos - field.scroll()) as u16, modal_y + 2 + form.active_field as u16));
+        }
+        None
+    }
+}
+
+// The screen offered when the config file does not parse, before the launcher starts
+pub struct RecoveryView<'a> {
+    pub error: &'a ParseError,
+    // Outcome of the last attempt, e.g. an editor that could not be started
+    pub status: Option<&'a str>,
+    pub has_backup: bool,
+}
+
+impl RecoveryView<'_> {
+    pub fn render(&self, (term_cols, term_rows): (u16, u16), frame: &mut Buffer) -> io::Result<()> {
+        let theme = ActiveTheme::from_config(&Config::default());
+        let modal_width: u16 = 76;
+        let modal_height: u16 = 14;
+        let modal_x = (term_cols.saturating_sub(modal_width)) / 2;
+        let modal_y = (term_rows.saturating_sub(modal_height)) / 2;
+        let inner = (modal_width - 6) as usize;
+        let fit = |s: &str| -> String { sanitize_for_tui(s).chars().take(inner).collect() };
+        let error = self.error;
+
+        draw_modal_box(frame, (modal_x, modal_y, modal_width, modal_height), "Config Error", Color::Red)?;
+
+        // Location and message
+        let text_x = modal_x + 3;
+        draw!(frame, cursor::MoveTo(text_x, modal_y + 2), style::SetForegroundColor(theme.title), style::SetAttribute(style::Attribute::Bold));
+        write!(frame, "{}", fit(&error.path.display().to_string()))?;
+        draw!(frame, style::SetAttribute(style::Attribute::Reset), style::ResetColor, cursor::MoveTo(text_x, modal_y + 3), style::SetForegroundColor(theme.text));
+        write!(frame, "{}", fit(&format!("Line {}, column {}: {}", error.line, error.column, error.message)))?;
+
+        // Offending line with a caret under the column
+        let line_label = format!("{} | ", error.line);
+        draw!(frame, cursor::MoveTo(text_x, modal_y + 5), style::SetForegroundColor(theme.dim));
+        write!(frame, "{}", line_label)?;
+        draw!(frame, style::SetForegroundColor(theme.text));
+        write!(frame, "{}", fit(&error.source_line).chars().take(inner.saturating_sub(line_label.len())).collect::<String>())?;
+        let caret_offset = (line_label.len() + error.column - 1).min(inner) as u16;
+        draw!(frame, cursor::MoveTo(text_x + caret_offset, modal_y + 6), style::SetForegroundColor(Color::Red), style::SetAttribute(style::Attribute::Bold));
+        write!(frame, "^")?;
+        draw!(frame, style::SetAttribute(style::Attribute::Reset), style::ResetColor);
+
+        if let Some(msg) = self.status {
+            draw!(frame, cursor::MoveTo(text_x, modal_y + 8), style::SetForegroundColor(Color::Yellow));
+            write!(frame, "{}", fit(msg))?;
+            draw!(frame, style::ResetColor);
+        }
+
+        // Options
+        let backup_hint = if self.has_backup { "[b] Restore last good backup" } else { "[b] (no backup)" };
+        let lines = [
+            format!("[e] Open in $EDITOR      {}", backup_hint),
+            "[n] Start with an empty, unsaved config      [q] Quit".to_string(),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            draw!(frame, cursor::MoveTo(text_x, modal_y + 10 + i as u16), style::SetForegroundColor(theme.dim));
+            write!(frame, "{}", line)?;
+        }
+        draw!(frame, style::ResetColor);
+        Ok(())
+    }
+}
+
+// Double-lined dialog frame with a centered title, cleared inside
+fn draw_modal_box(frame: &mut Buffer, (modal_x, modal_y, modal_width, modal_height): (u16, u16, u16, u16), title: &str, color: Color) -> io::Result<()> {
+    draw!(frame, style::SetForegroundColor(color));
+
+    // Top
+    let title = format!(" {} ", title);
+    let dash_len = (modal_width as usize - 2 - title.len()) / 2;
+    let left_dashes = "═".repeat(dash_len);
+    let right_dashes = "═".repeat(modal_width as usize - 2 - title.len() - dash_len);
+    draw!(frame, cursor::MoveTo(modal_x, modal_y));
+    write!(frame, "╔{}{}{}╗", left_dashes, title, right_dashes)?;
+
+    // Sides
+    for r in 1..modal_height - 1 {
+        draw!(frame, cursor::MoveTo(modal_x, modal_y + r));
+        write!(frame, "║{}║", " ".repeat((modal_width - 2) as usize))?;
+    }
+
+    // Bottom
+    draw!(frame, cursor::MoveTo(modal_x, modal_y + modal_height - 1));
+    write!(frame, "╚{}╝", "═".repeat((modal_width - 2) as usize))?;
+    draw!(frame, style::ResetColor);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ActiveTheme, RecoveryView};
+    use crate::app::{self, Event, State};
+    use crate::config::{App, Config, Conflict, ParseError};
+    use crate::history::History;
+    use crate::keys::Keymap;
+    use crate::render::{Backend, Buffer, Headless};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use std::io;
+    use std::path::PathBuf;
+    use std::time::Instant;
+
+    // Screens are compared at each of these terminal sizes
+    const SIZES: [(u16, u16); 3] = [(60, 20), (80, 24), (120, 32)];
+
+    fn app(id: &str, name: &str, key: &str, group: Option<&str>) -> App {
+        // Commands outside the allowlist, so the details pane does not depend on this machine
+        App {
+            id: id.to_string(),
+            name: name.to_string(),
+            key: key.to_string(),
+            cmd: format!("/opt/test/{}", name.to_lowercase()),
+            description: Some(format!("Starts {}", name)),
+            group: group.map(str::to_string),
+            ..App::default()
+        }
+    }
+
+    fn config() -> Config {
+        Config {
+            apps: vec![app("a1", "Firefox", "f", None), app("b2", "Vim", "v", Some("Editors")), app("c3", "Vivaldi", "w", None)],
+            ..Config::default()
+        }
+    }
+
+    fn key(code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn ctrl(c: char) -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL))
+    }
+
+    // The screen after the events, drawn as `run_app` draws it: text, then which cells are styled
+    fn launcher_screen(config: &Config, events: Vec<Event>, size: (u16, u16)) -> String {
+        let now = Instant::now();
+        let path = PathBuf::from("/home/user/.config/term-launcher/config.toml");
+        let mut state = State::new(config.clone(), Some(path), History::default(), size, now, 1_700_000_000, 42);
+        for event in events {
+            state = app::update(state, event, now).0;
+        }
+        let keymap = Keymap::from_config(&state.config).unwrap_or_default();
+        let (ranked, rows) = state.list();
+        let mut frame = Buffer::new(size.0, size.1);
+        let caret = state.view(&keymap, &ranked, &rows).render(&state.layout(), &mut frame).unwrap();
+        present(frame, caret, size, &state.config)
+    }
+
+    fn present(frame: Buffer, caret: Option<(u16, u16)>, size: (u16, u16), config: &Config) -> String {
+        let mut backend = Headless::new(size.0, size.1);
+        backend.present(frame, caret).unwrap();
+        let accent = ActiveTheme::from_config(config).accent;
+        let styles = backend.frame().map(|frame| frame.to_styles(accent)).unwrap_or_default();
+        format!("{}-- styles: # background, * bold, + accent --\n{}", backend.contents().0, styles)
+    }
+
+    // Compares with src/snapshots/<name>_<cols>x<rows>.txt; UPDATE_SNAPSHOTS=1 rewrites the files
+    fn assert_screens(name: &str, screen: impl Fn((u16, u16)) -> String) {
+        for size in SIZES {
+            let actual = screen(size);
+            let path = format!("{}/src/snapshots/{}_{}x{}.txt", env!("CARGO_MANIFEST_DIR"), name, size.0, size.1);
+            if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+                std::fs::write(&path, &actual).unwrap();
+                continue;
+            }
+            let expected = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+            assert!(actual == expected, "{} differs; the screen is now:\n{}", path, actual);
+        }
+    }
+
+    #[test]
+    fn empty_list() {
+        assert_screens("empty_list", |size| launcher_screen(&Config::default(), vec![], size));
+    }
+
+    #[test]
+    fn search_highlight() {
+        let events = || vec![key(KeyCode::Char('/')), key(KeyCode::Char('v')), key(KeyCode::Char('i'))];
+        assert_screens("search_highlight", |size| launcher_screen(&config(), events(), size));
+    }
+
+    #[test]
+    fn form_with_error() {
+        // Saving a form without a name or key
+        let events = || vec![ctrl('a'), key(KeyCode::Enter)];
+        assert_screens("form_with_error", |size| launcher_screen(&config(), events(), size));
+    }
+
+    #[test]
+    fn form_scrolls_long_values() {
+        // Typing past the end of the input box
+        let events = || {
+            let mut events = vec![ctrl('a'), key(KeyCode::Tab), key(KeyCode::Tab)];
+            events.extend("/usr/local/bin/a-rather-long-command-name --with-flags".chars().map(|c| key(KeyCode::Char(c))));
+            events
+        };
+        assert_screens("form_scrolls_long_values", |size| launcher_screen(&config(), events(), size));
+    }
+
+    #[test]
+    fn delete_confirmation() {
+        assert_screens("delete_confirmation", |size| launcher_screen(&config(), vec![ctrl('d')], size));
+    }
+
+    #[test]
+    fn theme_picker() {
+        let events = || vec![ctrl('t'), key(KeyCode::Down)];
+        assert_screens("theme_picker", |size| launcher_screen(&config(), events(), size));
+    }
+
+    #[test]
+    fn save_conflict() {
+        let events = || {
+            let path = PathBuf::from("/home/user/.config/term-launcher/config.toml");
+            vec![ctrl('d'), key(KeyCode::Char('y')), Event::Saved(Err(io::Error::other(Conflict { path })))]
+        };
+        assert_screens("save_conflict", |size| launcher_screen(&config(), events(), size));
+    }
+
+    #[test]
+    fn group_header() {
+        // Ungrouped apps come first, so the header is the third row
+        let events = || vec![key(KeyCode::Down), key(KeyCode::Down)];
+        assert_screens("group_header", |size| launcher_screen(&config(), events(), size));
+    }
+
+    #[test]
+    fn which_key() {
+        let config = Config { apps: vec![app("a1", "Gimp", "g", None), app("b2", "Glow", "gl", None), app("c3", "Glances", "gn", None)], ..Config::default() };
+        assert_screens("which_key", |size| launcher_screen(&config, vec![key(KeyCode::Char('g'))], size));
+    }
+
+    #[test]
+    fn recovery() {
+        let error = ParseError {
+            path: PathBuf::from("/home/user/.config/term-launcher/config.toml"),
+            line: 3,
+            column: 8,
+            message: "invalid string; expected `\"`".to_string(),
+            source_line: "name = \"Vim".to_string(),
+        };
+        assert_screens("recovery", |size| {
+            let mut frame = Buffer::new(size.0, size.1);
+            RecoveryView { error: &error, status: Some("The file still does not parse."), has_backup: true }.render(size, &mut frame).unwrap();
+            present(frame, None, size, &Config::default())
+        });
+    }
+}
@@ -0,0 +1,77 @@
+// Notices when the config file changes on disk.
+//
+// On Linux the directories holding the config (and its symlink target) are
+// watched with inotify, since editors, sync tools and our own atomic saves
+// replace the file by renaming rather than writing in place. Elsewhere, or if
+// inotify is unavailable, the file's modification time is polled instead.
+// Either way `changed` may report false positives; callers compare contents.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[cfg(target_os = "linux")]
+use inotify::{Inotify, WatchMask};
+
+pub struct ConfigWatcher {
+    path: PathBuf,
+    #[cfg(target_os = "linux")]
+    inotify: Option<(Inotify, Vec<std::ffi::OsString>)>,
+    last_seen: Option<(SystemTime, u64)>,
+}
+
+fn modified(path: &Path) -> Option<(SystemTime, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+#[cfg(target_os = "linux")]
+fn watch_dirs(path: &Path) -> Option<(Inotify, Vec<std::ffi::OsString>)> {
+    let inotify = Inotify::init().ok()?;
+    let mut names = Vec::new();
+    let targets = [Some(path.to_path_buf()), fs::canonicalize(path).ok()];
+    for target in targets.into_iter().flatten() {
+        let (Some(dir), Some(name)) = (target.parent(), target.file_name()) else { continue };
+        let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+        let mask = WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::CREATE | WatchMask::DELETE | WatchMask::MOVED_FROM;
+        inotify.watches().add(dir, mask).ok()?;
+        names.push(name.to_os_string());
+    }
+    Some((inotify, names))
+}
+
+impl ConfigWatcher {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            #[cfg(target_os = "linux")]
+            inotify: watch_dirs(path),
+            last_seen: modified(path),
+        }
+    }
+
+    // Non-blocking; true if the file may have changed since the last call
+    pub fn changed(&mut self) -> bool {
+        #[cfg(target_os = "linux")]
+        if let Some((inotify, names)) = self.inotify.as_mut() {
+            let mut buffer = [0u8; 4096];
+            let mut hit = false;
+            while let Ok(events) = inotify.read_events(&mut buffer) {
+                let mut any = false;
+                for event in events {
+                    any = true;
+                    hit |= event.name.is_some_and(|name| names.iter().any(|n| n == name));
+                }
+                if !any {
+                    break;
+                }
+            }
+            return hit;
+        }
+
+        let now = modified(&self.path);
+        let changed = now != self.last_seen;
+        self.last_seen = now;
+        changed
+    }
+}